@@ -1,8 +1,8 @@
 from pathlib import Path
-from typing import Set, Tuple, Dict, Any, Type, Union
+from typing import Iterator, Optional, Set, Tuple, Dict, Any, Type, Union
 from numpy import ndarray
 from GameEngines.abstract import AbsBoardState, AbsSaveModule
-from GameEngines.Avalam.utilsTypes import Move
+from GameEngines.Avalam.utilsTypes import Coords, Move
 from GameEngines.Avalam.SaveModule import AvalamSave
 
 class BoardState(AbsBoardState):
@@ -12,10 +12,24 @@ class BoardState(AbsBoardState):
     """
     _DEFAULT_SAVE_MOD = AvalamSave
 
+    SCHEMA_VERSION: int
+
     def __init__(self, *, save: Type['AbsSaveModule'] = _DEFAULT_SAVE_MOD): ...
 
     def __eq__(self, other: 'BoardState') -> bool: ...
 
+    def __hash__(self) -> int:
+        """ consistent with `__eq__`, so states can be used in sets and as dict keys """
+
+    def __lt__(self, other: 'BoardState') -> bool:
+        """ compares by `score_difference`, unlike `__eq__` which compares by value """
+
+    def __le__(self, other: 'BoardState') -> bool: ...
+
+    def __gt__(self, other: 'BoardState') -> bool: ...
+
+    def __ge__(self, other: 'BoardState') -> bool: ...
+
     @property
     def curr_pid(self) -> int: ...
 
@@ -32,15 +46,102 @@ class BoardState(AbsBoardState):
 
     def copy(self, *, cache=False) -> 'BoardState': ...
 
+    def __copy__(self) -> 'BoardState':
+        """ like `copy`, the copy has its own numpy arrays """
+
+    def __deepcopy__(self, memo: Dict[int, Any]) -> 'BoardState': ...
+
+    def lazy_copy(self) -> 'BoardState':
+        """ copies the state, sharing its numpy arrays with the original """
+
     def play(self, move: Move) -> 'BoardState': ...
 
     def get_legal_moves(self, *, cache=False) -> Set[Move]: ...
 
+    def legal_moves(self) -> 'LegalMoves': ...
+
+    def check_invariants(self):
+        """ raises a ValueError if the board, ratios and tower counts are inconsistent """
+
+    def is_legal(self, origin: Coords, dest: Coords) -> bool: ...
+
+    def moves_to(self, dest: Coords) -> Set[Move]:
+        """ the legal moves stacking a tower on `dest`, raises a ValueError if it is empty """
+
+    def legal_moves_array(self) -> ndarray:
+        """ returns the legal moves as a (N, 4) array of [origin_row, origin_col, dest_row, dest_col] rows """
+
     def score(self) -> Tuple[int, int]: ...
 
+    def score_difference(self) -> int: ...
+
+    def current_player(self) -> int: ...
+
+    def top_colors(self) -> ndarray: ...
+
+    def towers_dict(self) -> Dict[Tuple[int, int], Tuple[int, int]]: ...
+
+    def stable_towers(self) -> Tuple[int, int]: ...
+
+    def is_symmetric_to(self, other: 'BoardState') -> bool: ...
+
+    def movable_count(self) -> Tuple[int, int]: ...
+
+    def mobility(self) -> int: ...
+
+    def mobility_by_player(self) -> Tuple[int, int]: ...
+
+    def solve(self, max_plies: int, max_moves: int = 16, max_nodes: int = 4194304) -> Optional[int]: ...
+
     def winner(self) -> int: ...
 
+    def result(self) -> Any:
+        """ the `GameEngines.GameResult` of the game, a typed version of `winner` """
+
     @staticmethod
-    def load(file: Union[str, Path]) -> 'BoardState': ...
+    def load(file: Union[str, Path]) -> 'BoardState':
+        """ raises a `GameEngines.StateLoadError` if the file cannot be loaded """
 
     def save(self, file: Union[str, Path]): ...
+
+    def save_board_npy(self, board_path: Union[str, Path], ratios_path: Union[str, Path]): ...
+
+    @staticmethod
+    def from_board(board, ratios=None) -> 'BoardState':
+        """ raises a ValueError if a tower is taller than 5 or if its ratios do not sum to its height """
+
+    @staticmethod
+    def load_board_npy(board_path: Union[str, Path], ratios_path: Union[str, Path]) -> 'BoardState': ...
+
+    def to_dict(self) -> Dict[str, Any]: ...
+
+    @staticmethod
+    def from_dict(data: Dict[str, Any]) -> 'BoardState': ...
+
+    def to_msgpack(self) -> bytes: ...
+
+    @staticmethod
+    def from_msgpack(data: bytes) -> 'BoardState': ...
+
+    def fingerprint(self) -> bytes: ...
+
+    def fen(self) -> str: ...
+
+    @staticmethod
+    def from_fen(fen: str) -> 'BoardState':
+        """ raises a ValueError if `fen` is not an encoding given by `fen` """
+
+    def to_bytes(self) -> bytes: ...
+
+    @staticmethod
+    def from_bytes(data: bytes) -> 'BoardState': ...
+
+
+class LegalMoves:
+    """ the legal moves of a state, counted and tested without building them """
+    def __len__(self) -> int: ...
+
+    def __contains__(self, c_move: object) -> bool: ...
+
+    def __iter__(self) -> Iterator[Move]:
+        """ returns a one-shot iterator yielding the moves one at a time """
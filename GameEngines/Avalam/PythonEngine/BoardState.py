@@ -3,6 +3,7 @@ import numpy as np
 
 from GameEngines import BaseBoardState, AbsSaveModule
 from GameEngines.cache_utils import cache_moves
+from GameEngines.exceptions import check_legal
 
 from GameEngines.Avalam.repr import _repr
 from GameEngines.Avalam.SaveModule import AvalamSave
@@ -44,6 +45,7 @@ class BoardState(BaseBoardState):
         return _repr(self)
 
     def play(self, move: Move) -> 'BoardState':
+        check_legal(self, move)
         origin: Coords = move[0]
         dest: Coords = move[1]
 
@@ -1,9 +1,10 @@
 from pathlib import Path
 import json
 import numpy as np
-from typing import Union, Type, Any, Dict
+from typing import Optional, Union, Type, Any, Dict
 from GameEngines.abstract import AbsSaveModule, AbsBoardState
 from GameEngines import cache_utils
+from GameEngines.cache_utils import check_version
 from GameEngines.Avalam.utilsTypes import to_move
 
 
@@ -13,13 +14,15 @@ class AvalamSave(AbsSaveModule):
         board: [int],       // Flattened board from (9,9) to (81,)
         ratios: [int]        / Flattened ratio board from (2, 9,9) to (162,)
         turn: int,          // The current turn
-        curr_pid: int     // The active player
+        curr_pid: int,    // The active player
+        version: int      // The schema version of the engine that saved the file, if any
     }
     """
 
     @staticmethod
-    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState]) -> AbsBoardState:
+    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState], version: Optional[int] = None) -> AbsBoardState:
         data = json.loads(Path(file).read_text())
+        check_version(data, version)
 
         data["board"] = np.array(data["board"]).reshape((9, 9))
         data["ratios"] = np.array(data["ratios"]).reshape((2, 9, 9))
@@ -27,8 +30,10 @@ class AvalamSave(AbsSaveModule):
         return AvalamSave._put_data(data, state_type)
 
     @staticmethod
-    def save_state(file: Union[str, Path], state: AbsBoardState):
+    def save_state(file: Union[str, Path], state: AbsBoardState, version: Optional[int] = None):
         data = AvalamSave._get_data(state)
+        if version is not None:
+            data["version"] = version
         Path(file).write_text(json.dumps(data))
 
     @staticmethod
@@ -1,9 +1,10 @@
 from pathlib import Path
 import json
 import numpy as np
-from typing import Union, Type, Dict, Any
+from typing import Optional, Union, Type, Dict, Any
 from GameEngines.abstract import AbsBoardState, AbsSaveModule
 from GameEngines import cache_utils
+from GameEngines.cache_utils import check_version
 from GameEngines.Checkers.utilsTypes import to_move
 
 
@@ -13,12 +14,14 @@ class CheckersSave(AbsSaveModule):
         board: [int],         // Flattened board from (9,9) to (81,)
         cached_moves: [Move], // The move cache from self._cached_moves
         turn: int,            // The current turn
-        curr_pid: int         // The active player
+        curr_pid: int,        // The active player
+        version: int          // The schema version of the engine that saved the file, if any
     }
     """
     @staticmethod
-    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState]) -> AbsBoardState:
+    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState], version: Optional[int] = None) -> AbsBoardState:
         data = json.loads(Path(file).read_text())
+        check_version(data, version)
 
         data["board"] = np.array(data["board"]).reshape((7, 8))
         if data["cached_moves"] is not None:
@@ -27,8 +30,10 @@ class CheckersSave(AbsSaveModule):
         return CheckersSave._put_data(data, state_type)
 
     @staticmethod
-    def save_state(file: Union[str, Path], state: AbsBoardState):
+    def save_state(file: Union[str, Path], state: AbsBoardState, version: Optional[int] = None):
         data = CheckersSave._get_data(state)
+        if version is not None:
+            data["version"] = version
         Path(file).write_text(json.dumps(data))
 
     @staticmethod
@@ -4,6 +4,7 @@ from GameEngines.UltiTTT import Move
 from GameEngines.UltiTTT.repr import _repr
 from GameEngines.UltiTTT.SaveModule import UltiTTTSave
 from GameEngines.cache_utils import cache_moves
+from GameEngines.exceptions import check_legal
 import numpy as np
 
 
@@ -34,6 +35,7 @@ class BoardState(BaseBoardState):
         return _repr(self)
 
     def play(self, move: Move) -> 'BoardState':
+        check_legal(self, move)
         new_board = self.copy()
         new_board._turn += 1
 
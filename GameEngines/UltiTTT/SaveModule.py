@@ -1,9 +1,10 @@
 from pathlib import Path
 import json
 import numpy as np
-from typing import Union, Type, Dict, Any
+from typing import Optional, Union, Type, Dict, Any
 from GameEngines.abstract import AbsBoardState, AbsSaveModule
 from GameEngines import cache_utils
+from GameEngines.cache_utils import check_version
 from GameEngines.UltiTTT.utilsTypes import to_move
 
 
@@ -14,20 +15,28 @@ class UltiTTTSave(AbsSaveModule):
         win_state: [int],   // The move cache from self._cached_moves
         active_cell: int,   // The current active cell of the meta-board
         turn: int,          // The current turn
-        curr_pid: int       // The active player
+        curr_pid: int,      // The active player
+        version: int        // The schema version of the engine that saved the file, if any
     }
+    Saves written before `win_state` and `active_cell` were stored are still loaded: the won sub-boards are computed
+    from the board and the active cell from the optional `last_move` field, or the next move is free
     """
     @staticmethod
-    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState]) -> AbsBoardState:
+    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState], version: Optional[int] = None) -> AbsBoardState:
         data = json.loads(Path(file).read_text())
+        check_version(data, version)
 
+        if len(data.get("board", [])) != 81:
+            raise ValueError(f"the save file {file} does not hold a board of 81 cells")
         data["board"] = np.array(data["board"]).reshape((9, 9))
 
         return UltiTTTSave._put_data(data, state_type)
 
     @staticmethod
-    def save_state(file: Union[str, Path], state: AbsBoardState):
+    def save_state(file: Union[str, Path], state: AbsBoardState, version: Optional[int] = None):
         data = UltiTTTSave._get_data(state)
+        if version is not None:
+            data["version"] = version
         Path(file).write_text(json.dumps(data))
 
     @staticmethod
@@ -47,9 +56,26 @@ class UltiTTTSave(AbsSaveModule):
         state = state_type()
 
         state._board = data["board"]
-        state._win_state = data["win_state"]
-        state._active_cell = data["active_cell"]
+        # older saves may not hold the won sub-boards and the active cell
+        if "win_state" in data:
+            state._win_state = data["win_state"]
+        else:
+            state._win_state = [state_type._get_winner_of(cells.tolist()) for cells in data["board"]]
+
+        if "active_cell" in data:
+            state._active_cell = data["active_cell"]
+        else:
+            state._active_cell = UltiTTTSave._active_cell_after(data.get("last_move"), state._win_state)
         state._turn = data["turn"]
         state._curr_pid = data["curr_pid"]
 
         return state
+
+    @staticmethod
+    def _active_cell_after(last_move, win_state) -> int:
+        """ returns the sub-board forced by the last move, or -1 if it is unknown or the next move is free """
+        if last_move is None:
+            return -1
+
+        sub_i = 3 * last_move[1][0] + last_move[1][1]
+        return sub_i if win_state[sub_i] == 0 else -1
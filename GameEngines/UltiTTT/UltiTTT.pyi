@@ -1,6 +1,6 @@
 from distutils.command.build_py import build_py
 from pathlib import Path
-from typing import Set, Tuple, Dict, Any, Union
+from typing import Iterator, List, Optional, Set, Tuple, Dict, Any, Union
 from numpy import ndarray
 from GameEngines.abstract import AbsBoardState
 from GameEngines.Avalam.utilsTypes import Move
@@ -11,32 +11,125 @@ class BoardState(AbsBoardState):
     Rules for the game can be found online
     """
 
+    SCHEMA_VERSION: int
+
     def __init__(self): ...
 
     def __eq__(self, other: 'BoardState') -> bool: ...
 
+    def __lt__(self, other: 'BoardState') -> bool:
+        """ compares by `score_difference`, unlike `__eq__` which compares by value """
+
+    def __le__(self, other: 'BoardState') -> bool: ...
+
+    def __gt__(self, other: 'BoardState') -> bool: ...
+
+    def __ge__(self, other: 'BoardState') -> bool: ...
+
     @property
     def curr_pid(self) -> int: ...
 
     @property
     def turn(self) -> int: ...
 
+    @property
+    def meta_winner(self) -> int: ...
+
     @property
     def board(self) -> ndarray: ...
 
     def __repr__(self) -> str: ...
 
+    @staticmethod
+    def from_moves(moves: List[Move]) -> 'BoardState': ...
+
+    def moves_played(self) -> List[Move]:
+        """ raises a ValueError if the state was not played from the initial state """
+
     def copy(self, *, cache=False) -> 'BoardState': ...
 
+    def __copy__(self) -> 'BoardState':
+        """ like `copy`, the copy has its own numpy array """
+
+    def __deepcopy__(self, memo: Dict[int, Any]) -> 'BoardState': ...
+
+    def lazy_copy(self) -> 'BoardState':
+        """ copies the state, sharing its numpy arrays with the original """
+
     def play(self, move: Move,) -> 'BoardState': ...
 
     def get_legal_moves(self, *, cache=False) -> Set[Move]: ...
 
+    def legal_moves(self) -> 'LegalMoves': ...
+
+    def is_legal(self, move: Move) -> bool: ...
+
+    def legal_moves_array(self) -> ndarray:
+        """ returns the legal moves as a (N, 4) array of [sup_row, sup_col, sub_row, sub_col] rows """
+
     def score(self) -> Tuple[int, int]: ...
 
+    def score_difference(self) -> int: ...
+
+    def current_player(self) -> int: ...
+
+    def sub_board_counts(self, idx: int) -> Tuple[int, int, int]: ...
+
+    def sub_board_mobility(self) -> List[int]: ...
+
+    def is_symmetric_to(self, other: 'BoardState') -> bool: ...
+
+    def solve(self, max_plies: int, max_moves: int = 16, max_nodes: int = 4194304) -> Optional[int]: ...
+
     def winner(self) -> int: ...
 
+    def result(self) -> Any:
+        """ the `GameEngines.GameResult` of the game, a typed version of `winner` """
+
+    def is_draw(self) -> bool: ...
+
+    def win_for(self, player: int) -> bool: ...
+
     @staticmethod
-    def load(file: Union[str, Path]) -> 'BoardState': ...
+    def load(file: Union[str, Path]) -> 'BoardState':
+        """ raises a `GameEngines.StateLoadError` if the file cannot be loaded """
 
     def save(self, file: Union[str, Path]): ...
+
+    def save_board_npy(self, path: Union[str, Path]): ...
+
+    @staticmethod
+    def load_board_npy(path: Union[str, Path]) -> 'BoardState': ...
+
+    def to_dict(self) -> Dict[str, Any]: ...
+
+    @staticmethod
+    def from_dict(data: Dict[str, Any]) -> 'BoardState': ...
+
+    def to_msgpack(self) -> bytes: ...
+
+    @staticmethod
+    def from_msgpack(data: bytes) -> 'BoardState': ...
+
+    def fingerprint(self) -> bytes: ...
+
+    def fen(self) -> str: ...
+
+    @staticmethod
+    def from_fen(fen: str) -> 'BoardState':
+        """ raises a ValueError if `fen` is not an encoding given by `fen` """
+
+    def to_bytes(self) -> bytes: ...
+
+    @staticmethod
+    def from_bytes(data: bytes) -> 'BoardState': ...
+
+
+class LegalMoves:
+    """ the legal moves of a state, counted and tested without building them """
+    def __len__(self) -> int: ...
+
+    def __contains__(self, c_move: object) -> bool: ...
+
+    def __iter__(self) -> Iterator[Move]:
+        """ returns a one-shot iterator yielding the moves one at a time """
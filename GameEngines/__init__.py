@@ -2,6 +2,7 @@ try:
     from .GameEngines import *
 
     __doc__ = GameEngines.__doc__
+    __version__ = GameEngines.__version__
     if hasattr(GameEngines, "__all__"):
         __all__ = GameEngines.__all__
 
@@ -9,6 +10,7 @@ except ModuleNotFoundError:
     ...
 
 from .abstract import AbsPlayer, AbsBoardState, AbsSaveModule
+from .exceptions import IllegalMoveError
 from .Game import Game
 from .RandomPlayer import RandomPlayer
 from .BaseBoardState import BaseBoardState
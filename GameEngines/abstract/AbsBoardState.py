@@ -1,4 +1,4 @@
-from typing import Type, Union
+from typing import Optional, Type, Union
 from abc import ABC, abstractmethod
 import numpy as np
 from pathlib import Path
@@ -49,11 +49,12 @@ class AbsBoardState(ABC):
     @abstractmethod
     def play(self, move) -> 'AbsBoardState':
         """
-        method used to create the next state of the game if a move is played. The method does NOT verify that the move
-        is legal and will play it regardless
+        method used to create the next state of the game if a move is played. The Avalam and UltiTTT engines verify
+        that the move is legal and raise an IllegalMoveError otherwise
 
         :param move: the move to be played
         :return: a new static BoardState
+        :raise IllegalMoveError: if the move is not one of the legal moves of the state
         """
         ...
 
@@ -124,16 +125,20 @@ class AbsSaveModule(ABC):
 
     @staticmethod
     @abstractmethod
-    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState]) -> AbsBoardState:
+    def load_state(file: Union[str, Path], state_type: Type[AbsBoardState], version: Optional[int] = None) -> AbsBoardState:
         """
-        this is the default method to load data from a file into a BoardState
+        this is the default method to load data from a file into a BoardState.
+        When a `version` is given, it must raise a ValueError if the file was saved with another schema version.
+        The `version` argument is optional: the engines only pass it to save modules that accept it, and warn with a
+        UserWarning the first time they use a save module that does not
         """
         ...
 
     @staticmethod
     @abstractmethod
-    def save_state(file: Union[str, Path], state: AbsBoardState):
+    def save_state(file: Union[str, Path], state: AbsBoardState, version: Optional[int] = None):
         """
         this is the default method to save data from a BoardState to a file.
+        When a `version` is given, it must be saved in the file so `load_state` can check it
         """
         ...
\ No newline at end of file
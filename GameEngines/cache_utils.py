@@ -34,6 +34,18 @@ def ignore_cache(func):
         return func(self, *args, **kwargs)
     return wrapper
 
+def check_version(data: dict, version):
+    """
+    Checks that the data of a save file was written for the expected schema version. Files without a version predate
+    the versioning and are read as version 1.
+    :param data: the data loaded from the file.
+    :param version: the schema version expected by the engine, or None to skip the check.
+    """
+    saved = data.get("version", 1)
+    if version is not None and saved != version:
+        raise ValueError(f"the save file has schema version {saved}, but the engine expects version {version}")
+
+
 def get_cache(func):
     """
     Wrapper for the `_get_data` function that adds the move cache to the data dict if there was one.
@@ -0,0 +1,25 @@
+try:
+    from GameEngines.GameEngines import IllegalMoveError
+
+except ImportError:
+    class IllegalMoveError(ValueError):
+        """
+        Raised when playing an illegal move. The `move` and `reason` attributes give the move and why it is illegal
+        """
+
+
+def check_legal(state, move):
+    """
+    Checks that a move is legal in a state before it is played, the way the rust engines do in `play`.
+    :param state: the BoardState the move is played in.
+    :param move: the move to be played.
+    :raise IllegalMoveError: if the move is not one of the legal moves of the state.
+    """
+    if (tuple(move[0]), tuple(move[1])) in state.get_legal_moves():
+        return
+
+    reason = "it is not a legal move of the current player"
+    err = IllegalMoveError(f"illegal move {move}: {reason}")
+    err.move = move
+    err.reason = reason
+    raise err
@@ -0,0 +1,95 @@
+//! Timings of the Avalam move generation and of perft, against the neighbourhood scan the engine
+//! used before the bitboards. Run with
+//! `cargo bench --no-default-features --bench gen_moves`
+#![allow(clippy::needless_return)]
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use GameEngines::Core::Avalam::{AvalamState, PARALLEL_MIN_TOWERS, SIZE};
+use GameEngines::Core::perft::perft;
+use GameEngines::Core::Move;
+
+/// runs `f` `iters` times and returns the mean time of a call
+fn time<T>(iters: u32, mut f: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    for _ in 0..iters {
+        black_box(f());
+    }
+    return start.elapsed() / iters;
+}
+
+/// the move generation the engine had before the bitboards, kept as a baseline: an absolute
+/// board and a mask of the movable towers are built for every call, then the 8 neighbours of
+/// each movable tower are scanned into a vector of its moves
+fn baseline_moves(state: &AvalamState) -> Vec<Move> {
+    let abs_board: Vec<i64> = (0..SIZE * SIZE).map(|c| state.tower((c / SIZE, c % SIZE)).abs()).collect();
+    let towers: Vec<bool> = abs_board.iter().map(|&v| 0 < v && v < 5).collect();
+
+    return towers.iter().enumerate()
+        .filter_map(|(c, &movable)| if movable { Some(baseline_moves_for(&abs_board, c / SIZE, c % SIZE)) } else { None })
+        .flatten()
+        .collect();
+}
+
+/// the moves of the tower on `(i, j)` in [`baseline_moves`]
+fn baseline_moves_for(abs_board: &[i64], i: usize, j: usize) -> Vec<Move> {
+    let v = abs_board[SIZE * i + j];
+    return [(-1, -1), (-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0), (1, 1)].into_iter().filter_map(|(di, dj)| {
+        let (ni, nj) = (i.checked_add_signed(di)?, j.checked_add_signed(dj)?);
+        if ni >= SIZE || nj >= SIZE { return None; }
+
+        let pos = abs_board[SIZE * ni + nj] + v;
+        if v < pos && pos <= 5 { Some(((i, j), (ni, nj))) } else { None }
+    }).collect();
+}
+
+/// [`perft`] with the moves of [`baseline_moves`], a game being finished once it has no moves
+fn baseline_perft(state: &AvalamState, depth: u32) -> u64 {
+    if depth == 0 { return 1; }
+    let moves = baseline_moves(state);
+    if depth == 1 { return moves.len() as u64; }
+
+    return moves.into_iter().map(|c_move| baseline_perft(&state.play(c_move), depth - 1)).sum();
+}
+
+fn main() {
+    let initial = AvalamState::new();
+    // a middle game position, reached by always playing the first legal move
+    let middle = (0..20).fold(initial.clone(), |s, _| s.play(s.legal_moves()[0]));
+
+    for (name, state) in [("initial", &initial), ("middle", &middle)] {
+        let mut expected = baseline_moves(state);
+        let mut moves = state.legal_moves();
+        expected.sort_unstable();
+        moves.sort_unstable();
+        assert_eq!(moves, expected, "the baseline and the engine disagree on the {name} board");
+
+        let baseline = time(100_000, || baseline_moves(state));
+        let moves = time(100_000, || state.legal_moves());
+        let count = time(100_000, || state.legal_move_count());
+        println!("{name:>8}: baseline {baseline:?}, legal_moves {moves:?}, legal_move_count {count:?}");
+    }
+
+    // serial and row-parallel generation along a game, from the initial board to its last moves
+    println!("serial against rayon, PARALLEL_MIN_TOWERS = {PARALLEL_MIN_TOWERS}:");
+    let mut game = vec![initial.clone()];
+    while let Some(&c_move) = game.last().unwrap().legal_moves().first() {
+        game.push(game.last().unwrap().play(c_move));
+    }
+    for state in game.iter().step_by(8) {
+        let towers = state.score().0 + state.score().1;
+        let serial = time(10_000, || state.legal_moves());
+        let rayon = time(10_000, || state.row_parallel_moves());
+        println!("{towers:>8} towers: serial {serial:?}, rayon {rayon:?}");
+    }
+
+    for depth in 1..=3 {
+        let start = Instant::now();
+        let baseline = baseline_perft(&initial, depth);
+        let baseline_time = start.elapsed();
+        let start = Instant::now();
+        let nodes = perft(&initial, depth);
+        assert_eq!(nodes, baseline);
+        println!("perft({depth}) = {nodes} in {:?}, baseline {baseline_time:?}", start.elapsed());
+    }
+}
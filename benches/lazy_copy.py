@@ -0,0 +1,48 @@
+"""
+Time of a fixed-depth search on the rust engines when the nodes are copied with `copy` and with `lazy_copy`. Run it from the
+root of the repository, with the engines built, as `python benches/lazy_copy.py`
+"""
+import time
+
+from GameEngines.Avalam import BoardState as Avalam
+from GameEngines.UltiTTT import BoardState as UltiTTT
+
+
+def search(state, depth, copy, nodes):
+    """
+    negamax on the score difference, copying every node before expanding it the way bots copy the state they are given.
+    `nodes` is a one element list counting the states searched
+    """
+    nodes[0] += 1
+    state = copy(state)
+    if depth == 0 or state.winner() != 0:
+        return state.score_difference() * (1 if state.curr_pid == 1 else -1)
+
+    return max(-search(state.play(m), depth - 1, copy, nodes) for m in state.get_legal_moves())
+
+
+def bench(state, depth, copy, repeat=3):
+    """
+    returns the number of states of a search of `depth` plies and its best time over `repeat` searches
+    """
+    best = float("inf")
+    for _ in range(repeat):
+        nodes = [0]
+        start = time.perf_counter()
+        search(state, depth, copy, nodes)
+        best = min(best, time.perf_counter() - start)
+    return nodes[0], best
+
+
+def middle_game(engine, plies):
+    state = engine()
+    for _ in range(plies):
+        state = state.play(sorted(state.get_legal_moves())[0])
+    return state
+
+
+if __name__ == "__main__":
+    for name, state, depth in [("Avalam", middle_game(Avalam, 20), 2), ("UltiTTT", middle_game(UltiTTT, 10), 4)]:
+        nodes, copied = bench(state, depth, lambda s: s.copy())
+        _, lazy = bench(state, depth, lambda s: s.lazy_copy())
+        print(f"{name:>8}: {nodes:,} states at depth {depth}, copy {copied * 1e3:.1f} ms, lazy_copy {lazy * 1e3:.1f} ms")
@@ -0,0 +1,67 @@
+//! Counts the allocations made by the Avalam move generation along a whole game, run with
+//! `cargo bench --no-default-features --bench move_allocs`
+#![allow(clippy::needless_return)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use GameEngines::Core::Avalam::AvalamState;
+
+/// The system allocator, counting its allocations and reallocations
+struct Counting;
+
+static ALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        return System.alloc(layout);
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCS.fetch_add(1, Ordering::Relaxed);
+        return System.realloc(ptr, layout, new_size);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// returns the number of allocations made by `f`
+fn count(f: impl FnOnce()) -> usize {
+    let before = ALLOCS.load(Ordering::Relaxed);
+    f();
+    return ALLOCS.load(Ordering::Relaxed) - before;
+}
+
+fn main() {
+    // every position of a game always playing the first legal move
+    let mut states = vec![AvalamState::new()];
+    while let Some(&c_move) = states.last().unwrap().legal_moves().first() {
+        states.push(states.last().unwrap().play(c_move));
+    }
+
+    // a vector growing from empty, as the move generation did before it was presized
+    let growing = count(|| for state in &states {
+        let mut moves = Vec::new();
+        state.legal_moves_into(&mut moves);
+        black_box(moves);
+    });
+    let presized = count(|| for state in &states {
+        black_box(state.legal_moves());
+    });
+    let mut buffer = Vec::new();
+    let reused = count(|| for state in &states {
+        state.legal_moves_into(&mut buffer);
+        black_box(&buffer);
+    });
+
+    println!("allocations over the {} positions of a game:", states.len());
+    println!("  growing vector   {growing}");
+    println!("  legal_moves      {presized}");
+    println!("  reused buffer    {reused}");
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+use pyo3::prelude::*;
+use pyo3::{pyclass, pyfunction, pymethods, Python};
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use crate::Core::AnyState;
+use crate::Core::archive::{write_archive, Archive};
+use crate::Game::{any_state, py_state};
+
+
+/// A memory mapped archive of states written by `save_many`. States are decoded when accessed,
+/// so any of them can be read by index without loading the whole file
+#[pyclass(name = "StateArchive")]
+pub struct PyArchive {
+    archive: Archive,
+}
+
+#[pymethods]
+impl PyArchive {
+    fn __len__(&self) -> usize { return self.archive.len() }
+
+    fn __getitem__<'py>(&self, py: Python<'py>, index: isize) -> PyResult<Bound<'py, PyAny>> {
+        let len = self.archive.len() as isize;
+        let i = if index < 0 { index + len } else { index };
+
+        let state = usize::try_from(i).ok().and_then(|i| self.archive.get(i));
+        return match state {
+            Some(Ok(state)) => py_state(py, state),
+            Some(Err(reason)) => Err(PyValueError::new_err(reason)),
+            None => Err(PyIndexError::new_err("archive index out of range")),
+        }
+    }
+
+    fn __iter__(slf: Bound<'_, Self>) -> ArchiveIter {
+        return ArchiveIter { archive: slf.unbind(), index: 0 };
+    }
+}
+
+/// Iterator over the states of a `StateArchive`, in order
+#[pyclass]
+pub struct ArchiveIter {
+    archive: Py<PyArchive>,
+    index: usize,
+}
+
+#[pymethods]
+impl ArchiveIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> { return slf }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyAny>>> {
+        let state = self.archive.bind(py).borrow().archive.get(self.index);
+        self.index += 1;
+        return state.map(|s| py_state(py, s.map_err(PyValueError::new_err)?)).transpose();
+    }
+}
+
+/// packs states of a single game in one archive file at `path`, with a fixed-width record per
+/// state. The archive is read back with `load_many`
+#[pyfunction]
+pub fn save_many(states: Vec<Bound<'_, PyAny>>, path: PathBuf) -> PyResult<()> {
+    let states = states.iter().map(any_state).collect::<PyResult<Vec<AnyState>>>()?;
+    let game = match states.first() {
+        Some(state) => state.game(),
+        None => return Err(PyValueError::new_err("cannot save an empty list of states")),
+    };
+    if states.iter().any(|s| s.game() != game) {
+        return Err(PyValueError::new_err("all the states of an archive must be of the same game"));
+    }
+
+    write_archive(path, game, &states)?;
+    return Ok(());
+}
+
+/// memory maps an archive written by `save_many`
+#[pyfunction]
+pub fn load_many(path: PathBuf) -> PyResult<PyArchive> {
+    return Ok(PyArchive { archive: Archive::open(path)? });
+}
@@ -1,75 +1,58 @@
-use itertools::{Itertools};
-use ndarray::{Array2, Array3, array, ArrayView2};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use ndarray::{Array2, Array3};
+use ndarray_npy::{read_npy, write_npy};
 use numpy::{PyArray2, PyArray3, PyArrayMethods};
 use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
 use pyo3::{Py, pyclass, pymethods, Python, PyTypeInfo};
-use pyo3::types::{PyBool, PyNotImplemented, PySet, PyString, PyType};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::types::{PyBool, PyBytes, PyDict, PySet, PyString, PyType};
+use serde::{Deserialize, Serialize};
 use pyo3::class::basic::CompareOp;
+use crate::Core::{AnyState, Coords, GameState, Move};
+use crate::Core::Avalam::{AvalamState, MAX_HEIGHT, SIZE};
+use crate::Core::solve::{MAX_NODES, MAX_ROOT_MOVES};
+use crate::Game::{check_payload_version, counters, dict_item, illegal_move, load_state, moves_array, read_only, shared, solve_state, version_kwargs, LegalMoves, PyGameResult};
+
+/// version of the state representation written in the files of the save module
+const SCHEMA_VERSION: u32 = 1;
+
+/// version of the MessagePack payload, to be bumped whenever its fields change
+const MSGPACK_VERSION: u32 = 1;
+
+/// MessagePack payload of a state. The legal moves are not sent as they are regenerated from
+/// the board
+#[derive(Serialize, Deserialize)]
+struct AvalamPayload {
+    version: u32,
+    board: Vec<i64>,
+    ratios: Vec<i64>,
+    turn: u32,
+    curr_pid: u32,
+}
 
 
-type Coords = (usize, usize);
-type Move = (Coords, Coords);
-
-#[derive(Clone)]
+/// Python wrapper of an [`AvalamState`]. The board and ratios of the state are mirrored in numpy
+/// arrays, which are the external API of the class. The arrays are read-only, as the rules only
+/// read the wrapped state: they are replaced through the setters, which update the state.
+///
+/// The arrays are built on their first access, so the states a search plays and discards never
+/// allocate them.
 #[pyclass(subclass, dict)]
 pub struct RawAvalamState {
-    #[pyo3(get, set)]
-    _board: Py<PyArray2<i64>>,
-    #[pyo3(get, set)]
-    _ratios: Py<PyArray3<i64>>,
-    #[pyo3(get, set)]
-    _curr_pid: u32,
-    #[pyo3(get, set)]
-    _turn: u32,
+    _board: GILOnceCell<Py<PyArray2<i64>>>,
+    _ratios: GILOnceCell<Py<PyArray3<i64>>>,
 
     #[pyo3(get, set)]
-    _save_mod: Py<PyType>
+    _save_mod: Py<PyType>,
+
+    state: AvalamState,
 }
 
 unsafe impl Send for RawAvalamState {}
 
 impl RawAvalamState {
-    /// returns a raw avalam board in the initial state
-    fn base_array() -> Array2<i64>{
-        return array![
-            [ 0,  0,  1, -1,  0,  0,  0,  0,  0],
-            [ 0,  1, -1,  1, -1,  0,  0,  0,  0],
-            [ 0, -1,  1, -1,  1, -1,  1,  0,  0],
-            [ 0,  1, -1,  1, -1,  1, -1,  1, -1],
-            [ 1, -1,  1, -1,  0, -1,  1, -1,  1],
-            [-1,  1, -1,  1, -1,  1, -1,  1,  0],
-            [ 0,  0,  1, -1,  1, -1,  1, -1,  0],
-            [ 0,  0,  0,  0, -1,  1, -1,  1,  0],
-            [ 0,  0,  0,  0,  0, -1,  1,  0,  0],
-        ]
-    }
-
-    /// returns the ration table of an avalam board in the initial state
-    fn base_ratios() -> Array3<i64>{
-        return array![[
-            [0, 0, 1, 0, 0, 0, 0, 0, 0],
-            [0, 1, 0, 1, 0, 0, 0, 0, 0],
-            [0, 0, 1, 0, 1, 0, 1, 0, 0],
-            [0, 1, 0, 1, 0, 1, 0, 1, 0],
-            [1, 0, 1, 0, 0, 0, 1, 0, 1],
-            [0, 1, 0, 1, 0, 1, 0, 1, 0],
-            [0, 0, 1, 0, 1, 0, 1, 0, 0],
-            [0, 0, 0, 0, 0, 1, 0, 1, 0],
-            [0, 0, 0, 0, 0, 0, 1, 0, 0],
-        ], [
-            [0, 0, 0, 1, 0, 0, 0, 0, 0],
-            [0, 0, 1, 0, 1, 0, 0, 0, 0],
-            [0, 1, 0, 1, 0, 1, 0, 0, 0],
-            [0, 0, 1, 0, 1, 0, 1, 0, 1],
-            [0, 1, 0, 1, 0, 1, 0, 1, 0],
-            [1, 0, 1, 0, 1, 0, 1, 0, 0],
-            [0, 0, 0, 1, 0, 1, 0, 1, 0],
-            [0, 0, 0, 0, 1, 0, 1, 0, 0],
-            [0, 0, 0, 0, 0, 1, 0, 0, 0],
-        ],
-        ]
-    }
-
     fn default_save_mod() -> Py<PyType> {
         Python::with_gil(|_py| {
             let SaveModule = _py.import("GameEngines.Avalam.SaveModule").unwrap();
@@ -77,37 +60,50 @@ impl RawAvalamState {
         })
     }
 
-    fn _moves_for(abs_board: &Array2<i64>, i: usize, j: usize) -> Vec<Move> {
-        let (_i, _j) = (isize::try_from(i).unwrap(), isize::try_from(j).unwrap());
-        let v = abs_board[(i, j)];
-
-        return [
-            if _i-1 >= 0 && _j-1 >= 0 { Some(((i-1, j-1), abs_board[(i-1, j-1)] + v)) } else { None },
-            if _i-1 >= 0              { Some(((i-1, j  ), abs_board[(i-1, j  )] + v)) } else { None },
-            if _i-1 >= 0 && _j+1 <= 8 { Some(((i-1, j+1), abs_board[(i-1, j+1)] + v)) } else { None },
-            if              _j-1 >= 0 { Some(((i  , j-1), abs_board[(i  , j-1)] + v)) } else { None },
-            if              _j+1 <= 8 { Some(((i  , j+1), abs_board[(i  , j+1)] + v)) } else { None },
-            if _i+1 <= 8 && _j-1 >= 0 { Some(((i+1, j-1), abs_board[(i+1, j-1)] + v)) } else { None },
-            if _i+1 <= 8              { Some(((i+1, j  ), abs_board[(i+1, j  )] + v)) } else { None },
-            if _i+1 <= 8 && _j+1 <= 8 { Some(((i+1, j+1), abs_board[(i+1, j+1)] + v)) } else { None },
-        ].into_iter().filter_map(|option| match option {
-            None => { None }
-            Some((end, pos)) => {
-                if (v < pos) && (pos <= 5) { Some(((i, j), end)) } else { None }
-            }
-        }).collect_vec();
+    /// wraps a state in a python Avalam State object
+    fn from_state(state: AvalamState, save_mod: Py<PyType>) -> Self {
+        return RawAvalamState {
+            _board: GILOnceCell::new(),
+            _ratios: GILOnceCell::new(),
+            _save_mod: save_mod,
+            state,
+        };
     }
 
-    fn _has_moves(&self, py: Python) -> bool {
-        let board:ArrayView2<i64> = unsafe { self._board.bind(py).as_array() };
-        let abs_board = board.mapv(|v|v.abs());
-        let towers = abs_board.map(|&v| ((0 < v) & (v < 5)));
+    /// returns the numpy board of the state, built on its first access
+    fn board_py(&self, py: Python) -> PyResult<&Py<PyArray2<i64>>> {
+        return self._board.get_or_try_init(py, || -> PyResult<_> {
+            return Ok(read_only(PyArray2::from_owned_array(py, Self::board_array(&self.state)))?.unbind());
+        });
+    }
 
-        return towers.indexed_iter().find(|((i, j), &v)| {
-            if v { Self::_moves_for(&abs_board, *i, *j).len() > 0 }
-            else { false }
-        }).is_some();
+    /// returns the numpy ratios of the state, built on their first access
+    fn ratios_py(&self, py: Python) -> PyResult<&Py<PyArray3<i64>>> {
+        return self._ratios.get_or_try_init(py, || -> PyResult<_> {
+            return Ok(read_only(PyArray3::from_owned_array(py, Self::ratios_array(&self.state)))?.unbind());
+        });
     }
+
+    fn board_array(state: &AvalamState) -> Array2<i64> {
+        return Array2::from_shape_fn((SIZE, SIZE), |pos| state.tower(pos));
+    }
+
+    fn ratios_array(state: &AvalamState) -> Array3<i64> {
+        return Array3::from_shape_fn((2, SIZE, SIZE), |(c, i, j)| state.ratio((i, j))[c]);
+    }
+
+    /// wraps a state in a python Avalam State object sharing this object's save module
+    pub(crate) fn wrap(&self, py: Python, state: AvalamState) -> Self {
+        return Self::from_state(state, self._save_mod.clone_ref(py));
+    }
+
+    /// wraps a state in a python Avalam State object using the default save module
+    pub(crate) fn from_core(state: AvalamState) -> Self {
+        return Self::from_state(state, Self::default_save_mod());
+    }
+
+    /// the wrapped state
+    pub(crate) fn state(&self) -> &AvalamState { return &self.state }
 }
 
 #[pymethods]
@@ -115,127 +111,197 @@ impl RawAvalamState {
     #[new]
     #[pyo3(signature=(save_module=None))]
     /// Creates the initial Avalam State python object
-    fn new<'py>(py: Python<'py>, save_module: Option<Bound<'_, PyType>>) -> PyResult<Self>{
+    fn new(save_module: Option<Bound<'_, PyType>>) -> PyResult<Self>{
         let avalam_save: Py<PyType> = match save_module {
             None => { Self::default_save_mod()}
             Some(save_mod) => {save_mod.unbind()}
         };
 
-        let board = PyArray2::from_owned_array(py, RawAvalamState::base_array());
-        let ratios = PyArray3::from_owned_array(py, RawAvalamState::base_ratios());
+        return Ok(Self::from_state(AvalamState::new(), avalam_save));
+    }
 
-        return Ok(RawAvalamState {
-            _board: board.unbind(),
-            _ratios: ratios.unbind(),
-            _turn: 0,
-            _curr_pid: 1,
-            _save_mod: avalam_save
-        })
+    /// `==` and `!=` compare the states by value, while `<`, `<=`, `>` and `>=` compare them by
+    /// `score_difference`, so a list of states can be sorted by score. Two states can therefore
+    /// be neither smaller nor greater than each other without being equal. Any other type is
+    /// left to python with NotImplemented
+    fn __richcmp__<'py>(&self, py: Python<'py>, other: &Bound<'py, PyAny>, op: CompareOp) -> PyResult<PyObject> {
+        let Ok(other) = other.downcast::<Self>() else { return Ok(py.NotImplemented()); };
+        let other = other.borrow();
+        let (lhs, rhs) = (self.state.score_difference(), other.state.score_difference());
+        let res = match op {
+            CompareOp::Eq => self.state == other.state,
+            CompareOp::Ne => self.state != other.state,
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Ge => lhs >= rhs,
+        };
+        return Ok(PyBool::new(py, res).to_owned().into_any().unbind());
     }
 
-    fn __richcmp__<'py>(&self, py: Python<'py>, other: &Self, op: CompareOp) -> PyResult<Bound<'py, PyBool>> {
-        return match op {
-            CompareOp::Eq => {
-                let board_eq = std::iter::zip(
-                    self._board.bind(py).to_owned_array(),
-                    other._board.bind(py).to_owned_array()
-                ).all(|(a, b)| a == b);
+    /// hashes the wrapped state, so that states equal under `==` have the same hash and can be
+    /// used in sets and as dict keys. The numpy arrays are not hashed, as they only mirror it
+    fn __hash__(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.state.hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    /// copies and returns a python avalam State object
+    fn copy<'py>(&self, py: Python<'py>) -> PyResult<Self> {
+        counters::count_copy();
+        return Ok(self.wrap(py, self.state.clone()));
+    }
 
-                let ratios_eq = std::iter::zip(
-                    self._ratios.bind(py).to_owned_array(),
-                    other._ratios.bind(py).to_owned_array()
-                ).all(|(a, b)| a == b);
+    /// implements `copy.copy` as `copy`: the copy has its own numpy arrays, so modifying or
+    /// replacing them never affects this object. Use `lazy_copy` to share them instead
+    fn __copy__<'py>(&self, py: Python<'py>) -> PyResult<Self> {
+        return self.copy(py);
+    }
 
-                let turn_eq = self._turn == other._turn;
-                let curr_pid_eq = self._curr_pid == other._curr_pid;
+    /// implements `copy.deepcopy` as `copy`, which already gives fresh numpy arrays. The
+    /// wrapped state holds no python objects, so `memo` is not needed
+    fn __deepcopy__<'py>(&self, py: Python<'py>, _memo: &Bound<'py, PyAny>) -> PyResult<Self> {
+        return self.copy(py);
+    }
 
-                let res = board_eq && ratios_eq && turn_eq && curr_pid_eq;
-                Ok(PyBool::new(py, res).to_owned())
-            },
-            _ => { Err(PyErr::new::<PyNotImplemented, _>("")) },
-        }
+    /// copies the State object without copying its numpy arrays: the arrays this object already
+    /// built are shared with the copy, and the others are built separately on their first access.
+    /// The shared arrays must not be modified in place
+    fn lazy_copy<'py>(&self, py: Python<'py>) -> Self {
+        counters::count_copy();
+        return RawAvalamState {
+            _board: shared(py, &self._board),
+            _ratios: shared(py, &self._ratios),
+            _save_mod: self._save_mod.clone_ref(py),
+            state: self.state.clone(),
+        };
     }
 
-    /// copies and returns a python avalam State object
-    fn copy<'py>(&self, py: Python<'py>) -> PyResult<Self> {
+    /// play an action on the Avalam State and returns the following State object. It raises an
+    /// `IllegalMoveError` if the move is not legal
+    fn play<'py>(&self, py: Python<'py>, c_move: Move) -> PyResult<Self> {
+        self.state.check_move(c_move).map_err(|reason| illegal_move(py, c_move, reason))?;
+        counters::count_play();
+        return Ok(self.wrap(py, self.state.play(c_move)));
+    }
 
-        let board = unsafe { PyArray2::new(py, self._board.bind(py).dims(), false) };
-        let ratios = unsafe { PyArray3::new(py, self._ratios.bind(py).dims(), false) };
+    /// standard implementation of the `get_legal_moves` python method. it returns the legal
+    /// actions the specified player can take. In the case of the Avalam game, both players can
+    /// play the same set of moves
+    fn get_legal_moves<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PySet>> {
+        return PySet::new(py, self.state.legal_moves());
+    }
 
-        self._board.bind(py).copy_to(&board)?;
-        self._ratios.bind(py).copy_to(&ratios)?;
+    /// returns whether moving the tower on `origin` onto `dest` is legal: both cells hold a
+    /// tower, they are neighbours and the merged tower is at most 5 tall. Only this pair is
+    /// checked, so it is a cheap way to validate a move given by a user before playing it
+    fn is_legal(&self, origin: Coords, dest: Coords) -> bool {
+        return self.state.is_legal((origin, dest));
+    }
 
-        Ok(RawAvalamState {
-            _board: board.unbind(),
-            _ratios: ratios.unbind(),
-            _turn: self._turn,
-            _curr_pid: self._curr_pid,
-            _save_mod: self._save_mod.clone_ref(py)
-        })
+    /// returns the legal moves stacking a neighbouring tower on top of `dest`, to find the moves
+    /// capturing or reinforcing a given tower. It raises a ValueError if `dest` is empty
+    fn moves_to<'py>(&self, py: Python<'py>, dest: Coords) -> PyResult<Bound<'py, PySet>> {
+        let moves = self.state.moves_to(dest)
+            .map_err(|reason| PyValueError::new_err(format!("invalid destination {dest:?}: {reason}")))?;
+        return PySet::new(py, moves);
     }
 
-    /// play an action on the Avalam State and returns the following State object
-    fn play<'py>(&mut self, py: Python<'py>, c_move: Move) -> PyResult<Self> {
-        let origin = c_move.0;
-        let dest = c_move.1;
+    /// returns the legal moves as a (N, 4) numpy array, with one `[origin_row, origin_col,
+    /// dest_row, dest_col]` row per move
+    fn legal_moves_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<i64>> {
+        return PyArray2::from_owned_array(py, moves_array(&self.state.legal_moves()));
+    }
 
-        let mut new_board = self.copy(py)?;
-        new_board._turn += 1;
+    /// returns the legal moves of the state as a `LegalMoves` object, which gives their number
+    /// and membership tests without building the whole set
+    fn legal_moves(&self) -> LegalMoves {
+        return LegalMoves::new(AnyState::Avalam(self.state.clone()));
+    }
 
-        // board Update
-        let b_ref = new_board._board.bind(py);
-        let top = unsafe{ *b_ref.uget(origin) };
-        let bottom = unsafe{ *b_ref.uget(dest) };
-        let final_val = top.signum() * bottom.abs() + top;
+    /// checks that the board, the ratios and the tower counts of the state are consistent, to
+    /// debug the updates made by `play`. It raises a ValueError describing the first broken
+    /// invariant. As it scans the whole board, it is meant for tests and debug assertions
+    fn check_invariants(&self) -> PyResult<()> {
+        return self.state.check_invariants().map_err(PyValueError::new_err);
+    }
 
-        b_ref.set_item(origin, 0).expect("Origin outside expected range");
-        b_ref.set_item(dest, final_val).expect("Destination outside expected range");
+    /// returns the current score of the State. In the case of Avalam, this means the number of
+    /// towers controlled by each player
+    fn score(&self) -> (usize, usize){
+        return self.state.score();
+    }
 
-        // Ratios Update
-        let r_ref = new_board._ratios.bind(py);
-        let top_0 = unsafe{ *r_ref.uget((0, origin.0, origin.1)) };
-        let top_1 = unsafe{ *r_ref.uget((1, origin.0, origin.1)) };
+    /// returns the winner of the game under optimal play of both players (-1 for a tie) if it is
+    /// decided within `max_plies` moves, and None otherwise. The tree is searched without holding
+    /// the GIL, so it raises a ValueError if the state has more than `max_moves` legal moves, and
+    /// it returns None if the result is not found within `max_nodes` states
+    #[pyo3(signature=(max_plies, max_moves=MAX_ROOT_MOVES, max_nodes=MAX_NODES))]
+    fn solve(&self, py: Python<'_>, max_plies: u32, max_moves: usize, max_nodes: u64) -> PyResult<Option<i64>> {
+        return solve_state(py, &self.state, max_plies, max_moves, max_nodes);
+    }
 
-        let bottom_0 = unsafe{ *r_ref.uget((0, dest.0, dest.1)) };
-        let bottom_1 = unsafe{ *r_ref.uget((1, dest.0, dest.1)) };
+    /// returns whether the two states are equal under one of the 8 rotations and reflections of
+    /// the board. Positions that only differ by their orientation have the same outcome, so a
+    /// search can treat them as a single node
+    fn is_symmetric_to(&self, other: &Self) -> bool {
+        return self.state.is_symmetric_to(&other.state);
+    }
 
-        r_ref.set_item((0, origin.0, origin.1), 0).expect("Origin outside expected range");
-        r_ref.set_item((1, origin.0, origin.1), 0).expect("Origin outside expected range");
+    /// returns the number of towers of each player that can never be merged again. They are a
+    /// lower bound of the final score of both players
+    fn stable_towers(&self) -> (usize, usize) {
+        return self.state.stable_towers();
+    }
 
-        r_ref.set_item((0, dest.0, dest.1), top_0 + bottom_0).expect("Destination outside expected range");
-        r_ref.set_item((1, dest.0, dest.1), top_1 + bottom_1).expect("Destination outside expected range");
+    /// returns the number of towers of each player that have at least one legal move. Unlike the
+    /// number of legal moves, each tower is only counted once
+    fn movable_count(&self) -> (usize, usize) {
+        return self.state.movable_count();
+    }
 
-        new_board._curr_pid = (self._curr_pid % 2) + 1;
-        return Ok(new_board);
+    /// returns the number of legal moves, as a cheap evaluation feature: they are counted without
+    /// being built, unlike `len(get_legal_moves())`
+    fn mobility(&self) -> usize {
+        return self.state.mobility();
     }
 
-    /// standard implementation of the `get_legal_moves` python method. it returns the legal
-    /// actions the specified player can take. In the case of the Avalam game, both players can
-    /// play the same set of moves
-    fn get_legal_moves<'py>(&mut self, py: Python<'py>) -> PyResult<Bound<'py, PySet>> {
-        let board:ArrayView2<i64> = unsafe { self._board.bind(py).as_array() };
-        let abs_board = board.mapv(|v|v.abs());
-        let towers = abs_board.map(|&v| ((0 < v) & (v < 5)));
+    /// returns the number of legal moves moving a tower of player 1 and of player 2, by the color
+    /// on top of the moved tower. It sums to `mobility`
+    fn mobility_by_player(&self) -> (usize, usize) {
+        return self.state.mobility_by_player();
+    }
 
-        let moves = towers.indexed_iter().filter_map(|((i, j), &v)| {
-            if v {
-                Some(Self::_moves_for(&abs_board, i, j))
+    /// returns a dict mapping the `(row, col)` of every tower to its `(owner_sign, height)`, where
+    /// the sign is 1 for player 1 and -1 for player 2
+    fn towers_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let towers = PyDict::new(py);
+        for pos in (0..SIZE).flat_map(|i| (0..SIZE).map(move |j| (i, j))) {
+            let v = self.state.tower(pos);
+            if v != 0 {
+                towers.set_item(pos, (v.signum(), v.abs()))?;
+            }
+        }
+        return Ok(towers);
+    }
 
-            } else { None }
-        }).flatten();
+    /// returns a (9, 9) matrix of the color controlling each tower, ignoring its height: 1 for
+    /// player 1, -1 for player 2 and 0 for empty cells
+    fn top_colors<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<i8>> {
+        let colors = Array2::from_shape_fn((SIZE, SIZE), |pos| self.state.top_color(pos));
+        return PyArray2::from_owned_array(py, colors);
+    }
 
-        return PySet::new(py, moves);
+    /// returns the id (1 or 2) of the player to move. Player 1 controls the positive towers of the
+    /// board and player 2 the negative ones
+    fn current_player(&self) -> i64 {
+        return i64::from(self.state.curr_pid);
     }
 
-    /// returns the current score of the State. In the case of Avalam, this means the number of
-    /// towers controlled by each player
-    fn score<'py>(&self, py: Python<'py>) -> (usize, usize){
-        let array = unsafe { self._board.bind(py).as_array() };
-        array.fold((0, 0), |b, &v| {
-            if v > 0 { return (b.0 + 1, b.1); }
-            if v < 0 { return (b.0, b.1 + 1); }
-            return b;
-        })
+    /// returns the score of player 1 minus the score of player 2, the key used to order states
+    fn score_difference(&self) -> i64 {
+        return self.state.score_difference();
     }
 
     /// return the current winner of the game.
@@ -245,22 +311,30 @@ impl RawAvalamState {
     /// If the game is a tie it returns -1
     ///
     /// Otherwise, it returns the player id of the winner
-    fn winner<'py>(&mut self, py: Python<'py>) -> PyResult<isize> {
-        // unfinished
-        if self._has_moves(py) { return Ok(0); }
+    fn winner(&self) -> i64 {
+        return self.state.winner();
+    }
 
-        let (p1, p2) = self.score(py);
-        // tie
-        if p1 == p2 { return Ok(-1) }
-        // winner
-        return Ok(isize::from(p1 < p2) + 1)
+    /// returns the status of the game as a `GameResult`, the typed version of `winner`
+    fn result(&self) -> PyGameResult {
+        return GameState::result(&self.state).into();
     }
 
-    fn save<'py>(slf: Bound<'py, Self>, file: Bound<'py, PyAny>) {
-        let save_mod = slf.getattr("_save_mod").unwrap();
-        save_mod.call_method("save_state", (file, slf), None).unwrap();
+    /// version of the state representation, given to the save module so files written for
+    /// another version are rejected when loaded
+    #[classattr]
+    const SCHEMA_VERSION: u32 = SCHEMA_VERSION;
+
+    /// saves the state in a file with its save module, tagged with the `SCHEMA_VERSION`
+    fn save<'py>(slf: Bound<'py, Self>, file: Bound<'py, PyAny>) -> PyResult<()> {
+        let save_mod = slf.getattr("_save_mod")?;
+        let kwargs = version_kwargs(&save_mod, "save_state", SCHEMA_VERSION)?;
+        save_mod.call_method("save_state", (file, slf), kwargs.as_ref())?;
+        return Ok(());
     }
 
+    /// loads a state from a file with the save module, checking its `SCHEMA_VERSION`. It raises a
+    /// `StateLoadError` if the file cannot be loaded
     #[staticmethod]
     #[pyo3(signature=(file, save_module=None))]
     fn load<'py>(file: Bound<'py, PyString>, save_module: Option<Bound<'py, PyType>>) -> PyResult<Bound<'py, PyAny>> {
@@ -270,20 +344,252 @@ impl RawAvalamState {
         };
 
         let py = file.py();
-        avalam_save.bind(py.clone()).call_method(
-            "load_state",  (file, Self::type_object(py.clone())), None
-        )
+        let save_mod = avalam_save.bind(py);
+        let kwargs = version_kwargs(save_mod.as_any(), "load_state", SCHEMA_VERSION)?;
+        return load_state(save_mod, file, Self::type_object(py), kwargs.as_ref());
+    }
+
+    /// writes the board and the ratios of the state in numpy's `.npy` format, one array per file
+    fn save_board_npy(&self, board_path: PathBuf, ratios_path: PathBuf) -> PyResult<()> {
+        write_npy(&board_path, &Self::board_array(&self.state)).map_err(npy_error)?;
+        write_npy(&ratios_path, &Self::ratios_array(&self.state)).map_err(npy_error)?;
+        return Ok(());
+    }
+
+    /// reads a board and its ratios written in numpy's `.npy` format. As the files only hold the
+    /// towers, the turn is deduced from the number of towers left and the current player from
+    /// the turn
+    #[staticmethod]
+    #[pyo3(signature=(board_path, ratios_path, save_module=None))]
+    fn load_board_npy<'py>(board_path: PathBuf, ratios_path: PathBuf, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let board: Array2<i64> = read_npy(&board_path).map_err(npy_error)?;
+        let ratios: Array3<i64> = read_npy(&ratios_path).map_err(npy_error)?;
+
+        if board.dim() != (SIZE, SIZE) || ratios.dim() != (2, SIZE, SIZE) {
+            return Err(PyValueError::new_err(format!(
+                "expected a ({SIZE}, {SIZE}) board and (2, {SIZE}, {SIZE}) ratios, got {:?} and {:?}",
+                board.dim(), ratios.dim()
+            )));
+        }
+
+        let board: [[i64; SIZE]; SIZE] = std::array::from_fn(|i| std::array::from_fn(|j| board[(i, j)]));
+        let ratios: [[[i64; SIZE]; SIZE]; 2] = std::array::from_fn(|c| {
+            std::array::from_fn(|i| std::array::from_fn(|j| ratios[(c, i, j)]))
+        });
+        return Self::from_board(board, Some(ratios), save_module);
+    }
+
+    /// creates a state from its signed towers, as nested lists or a numpy array, and optionally
+    /// the ratios of every tower. Without ratios, every piece of a tower is of its top color. The
+    /// turn is deduced from the number of towers left and the current player from the turn.
+    ///
+    /// It raises a ValueError giving the offending cell if a tower is taller than 5 or if its
+    /// ratios do not sum to its height
+    #[staticmethod]
+    #[pyo3(signature=(board, ratios=None, save_module=None))]
+    fn from_board<'py>(board: [[i64; SIZE]; SIZE], ratios: Option<[[[i64; SIZE]; SIZE]; 2]>, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let state = AvalamState::from_board(&board, ratios.as_ref()).map_err(|(pos, reason)| {
+            PyValueError::new_err(format!("invalid cell {pos:?}: {reason}"))
+        })?;
+
+        let avalam_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, avalam_save));
+    }
+
+    /// returns a deterministic byte encoding of the position, meant to be hashed by user-side
+    /// transposition tables
+    fn fingerprint<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        return PyBytes::new(py, &self.state.fingerprint());
+    }
+
+    /// returns the fixed-width byte encoding of the whole state, read back with `from_bytes`
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        return PyBytes::new(py, &self.state.to_bytes());
+    }
+
+    /// creates a state from the fixed-width encoding given by `to_bytes`
+    #[staticmethod]
+    #[pyo3(signature=(data, save_module=None))]
+    fn from_bytes<'py>(data: &[u8], save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let state = AvalamState::from_bytes(data).map_err(PyValueError::new_err)?;
+
+        let save_mod: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, save_mod));
+    }
+
+    /// returns a human readable encoding of the state, read back with `from_fen`, to share
+    /// positions in bug reports and test cases. The grammar is given by [`AvalamState::to_fen`]: for
+    /// example, the first row of the initial board is `2+10-015`
+    fn fen(&self) -> String {
+        return self.state.to_fen();
+    }
+
+    /// creates a state from the encoding given by `fen`. It raises a ValueError describing the
+    /// error if the encoding is invalid
+    #[staticmethod]
+    #[pyo3(signature=(fen, save_module=None))]
+    fn from_fen<'py>(fen: &str, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let state = AvalamState::from_fen(fen).map_err(|reason| PyValueError::new_err(format!("invalid fen {fen:?}: {reason}")))?;
+
+        let avalam_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, avalam_save));
+    }
+
+    /// returns the state as a dict of plain python lists and ints, read back with `from_dict`.
+    /// The board is a (9, 9) nested list and the ratios a (2, 9, 9) nested list
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let board: [[i64; SIZE]; SIZE] = std::array::from_fn(|i| {
+            std::array::from_fn(|j| self.state.tower((i, j)))
+        });
+        let ratios: [[[i64; SIZE]; SIZE]; 2] = std::array::from_fn(|c| {
+            std::array::from_fn(|i| std::array::from_fn(|j| self.state.ratio((i, j))[c]))
+        });
+
+        let data = PyDict::new(py);
+        data.set_item("board", board)?;
+        data.set_item("ratios", ratios)?;
+        data.set_item("turn", self.state.turn)?;
+        data.set_item("curr_pid", self.state.curr_pid)?;
+        return Ok(data);
+    }
+
+    /// creates a state from the dict given by `to_dict`. It raises a ValueError if the towers and
+    /// ratios do not form a valid board or if the player to move is not 1 or 2
+    #[staticmethod]
+    #[pyo3(signature=(data, save_module=None))]
+    fn from_dict<'py>(data: &Bound<'py, PyDict>, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let board: [[i64; SIZE]; SIZE] = dict_item(data, "board")?;
+        let ratios: [[[i64; SIZE]; SIZE]; 2] = dict_item(data, "ratios")?;
+        let state = AvalamState::from_parts(&board, &ratios, dict_item(data, "turn")?, dict_item(data, "curr_pid")?)
+            .map_err(PyValueError::new_err)?;
+
+        let avalam_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, avalam_save));
+    }
+
+    /// returns a compact MessagePack encoding of the state, read back with `from_msgpack`
+    fn to_msgpack<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let payload = AvalamPayload {
+            version: MSGPACK_VERSION,
+            board: Self::board_array(&self.state).iter().copied().collect(),
+            ratios: Self::ratios_array(&self.state).iter().copied().collect(),
+            turn: self.state.turn,
+            curr_pid: self.state.curr_pid,
+        };
+        let data = rmp_serde::to_vec(&payload).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        return Ok(PyBytes::new(py, &data));
     }
 
+    /// creates a state from the MessagePack encoding given by `to_msgpack`. It raises a
+    /// `StateLoadError` if the payload was written with another version, and a ValueError if it
+    /// is malformed
+    #[staticmethod]
+    #[pyo3(signature=(data, save_module=None))]
+    fn from_msgpack<'py>(data: &[u8], save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        check_payload_version(data, MSGPACK_VERSION)?;
+        let payload: AvalamPayload = rmp_serde::from_slice(data).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        if payload.board.len() != SIZE * SIZE || payload.ratios.len() != 2 * SIZE * SIZE {
+            return Err(PyValueError::new_err("malformed payload, wrong board or ratios size"));
+        }
+
+        let board: [[i64; SIZE]; SIZE] = std::array::from_fn(|i| {
+            std::array::from_fn(|j| payload.board[i * SIZE + j])
+        });
+        let ratios: [[[i64; SIZE]; SIZE]; 2] = std::array::from_fn(|c| {
+            std::array::from_fn(|i| std::array::from_fn(|j| payload.ratios[(c * SIZE + i) * SIZE + j]))
+        });
+        let state = AvalamState::from_parts(&board, &ratios, payload.turn, payload.curr_pid)
+            .map_err(|reason| PyValueError::new_err(format!("malformed payload, {reason}")))?;
+
+        let avalam_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, avalam_save));
+    }
+
+    /// replaces the raw board and updates the wrapped state from it
+    #[setter(_board)]
+    fn set_board(&mut self, board: Bound<'_, PyArray2<i64>>) -> PyResult<()> {
+        let array = board.readonly();
+        let array = array.as_array();
+        if array.dim() != (SIZE, SIZE) {
+            return Err(PyValueError::new_err(format!("expected a ({SIZE}, {SIZE}) board, got {:?}", array.dim())));
+        }
+        if let Some((pos, v)) = array.indexed_iter().find(|&(_, &v)| v.abs() > i64::from(MAX_HEIGHT)) {
+            return Err(PyValueError::new_err(format!("invalid cell {pos:?}: the tower {v} is taller than {MAX_HEIGHT}")));
+        }
+
+        for (pos, &v) in array.indexed_iter() {
+            self.state.set_tower(pos, v);
+        }
+        self._board = GILOnceCell::new();
+        return Ok(());
+    }
+
+    /// replaces the raw ratios and updates the wrapped state from them
+    #[setter(_ratios)]
+    fn set_ratios(&mut self, ratios: Bound<'_, PyArray3<i64>>) -> PyResult<()> {
+        let array = ratios.readonly();
+        let array = array.as_array();
+        if array.dim() != (2, SIZE, SIZE) {
+            return Err(PyValueError::new_err(format!("expected (2, {SIZE}, {SIZE}) ratios, got {:?}", array.dim())));
+        }
+        if let Some(((c, i, j), v)) = array.indexed_iter().find(|&(_, &v)| !(0..=i64::from(MAX_HEIGHT)).contains(&v)) {
+            return Err(PyValueError::new_err(format!("invalid cell {:?}: the ratio {v} of color {c} is outside of 0..={MAX_HEIGHT}", (i, j))));
+        }
+
+        for ((c, i, j), &v) in array.indexed_iter() {
+            self.state.set_ratio(c, (i, j), v);
+        }
+        self._ratios = GILOnceCell::new();
+        return Ok(());
+    }
+
+    #[getter(_turn)]
+    fn get_raw_turn(&self) -> u32 { return self.state.turn }
+
+    #[setter(_turn)]
+    fn set_raw_turn(&mut self, turn: u32) { self.state.turn = turn }
+
+    #[getter(_curr_pid)]
+    fn get_raw_curr_pid(&self) -> u32 { return self.state.curr_pid }
+
+    #[setter(_curr_pid)]
+    fn set_raw_curr_pid(&mut self, curr_pid: u32) { self.state.curr_pid = curr_pid }
+
     #[getter]
-    fn turn(&self) -> u32 { return self._turn }
+    fn turn(&self) -> u32 { return self.state.turn }
 
     #[getter]
-    fn curr_pid(&self) -> u32 { return self._curr_pid }
+    fn curr_pid(&self) -> u32 { return self.state.curr_pid }
+
+    #[getter(_board)]
+    fn get_raw_board(&self, py: Python<'_>) -> PyResult<&Py<PyArray2<i64>>> { return self.board_py(py) }
+
+    #[getter(_ratios)]
+    fn get_raw_ratios(&self, py: Python<'_>) -> PyResult<&Py<PyArray3<i64>>> { return self.ratios_py(py) }
 
     #[getter]
-    fn board(&self) -> &Py<PyArray2<i64>> { return &self._board }
+    fn board(&self, py: Python<'_>) -> PyResult<&Py<PyArray2<i64>>> { return self.board_py(py) }
 
     #[getter]
-    fn ratios(&self) -> &Py<PyArray3<i64>> { return &self._ratios }
-}
\ No newline at end of file
+    fn ratios(&self, py: Python<'_>) -> PyResult<&Py<PyArray3<i64>>> { return self.ratios_py(py) }
+}
+
+fn npy_error(e: impl std::fmt::Display) -> PyErr {
+    return PyIOError::new_err(e.to_string());
+}
@@ -54,7 +54,7 @@ pub struct RawCheckersState {
 unsafe impl Send for RawCheckersState {}
 
 impl RawCheckersState {
-    fn base_array() -> Array2<i64> {
+    pub(crate) fn base_array() -> Array2<i64> {
         return array![
             [ 3, 3, 3, 1, 1, 3, 3, 3],
             [ 3, 3, 0, 1, 1, 1, 3, 3],
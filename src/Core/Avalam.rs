@@ -0,0 +1,609 @@
+//! The rules of the Avalam game
+use rayon::prelude::*;
+use crate::Core::{symmetry, Coords, Move, SYMMETRIES};
+
+/// width and height of the board
+pub const SIZE: usize = 9;
+const CELLS: usize = SIZE * SIZE;
+/// height of the tallest tower allowed on the board
+pub const MAX_HEIGHT: u8 = 5;
+/// number of legal moves of the initial board. Merges only ever remove towers or make them
+/// taller, so no later position of a game can have more moves than this
+const INITIAL_MOVES: usize = 292;
+/// number of towers of the initial board. Every move removes exactly one of them
+pub const INITIAL_TOWERS: usize = 48;
+/// size of the fixed-width encoding given by [`AvalamState::to_bytes`]
+pub const RECORD_SIZE: usize = 3 * CELLS + 5;
+/// number of towers from which [`AvalamState::par_legal_moves`] generates the rows in parallel.
+/// With fewer towers, a task per row costs more than the moves it generates. The `gen_moves`
+/// bench compares both paths at every board size to tune it on the target machine
+pub const PARALLEL_MIN_TOWERS: u32 = 32;
+/// bitboard mask of the first row of the board
+const ROW_MASK: u128 = (1u128 << SIZE) - 1;
+/// bitboards of the towers at most 0, 1, ..., [`MAX_HEIGHT`] tall, see [`AvalamState::fitting`]
+type Fitting = [u128; MAX_HEIGHT as usize + 1];
+
+const INITIAL_BOARD: [[i8; SIZE]; SIZE] = [
+    [ 0,  0,  1, -1,  0,  0,  0,  0,  0],
+    [ 0,  1, -1,  1, -1,  0,  0,  0,  0],
+    [ 0, -1,  1, -1,  1, -1,  1,  0,  0],
+    [ 0,  1, -1,  1, -1,  1, -1,  1, -1],
+    [ 1, -1,  1, -1,  0, -1,  1, -1,  1],
+    [-1,  1, -1,  1, -1,  1, -1,  1,  0],
+    [ 0,  0,  1, -1,  1, -1,  1, -1,  0],
+    [ 0,  0,  0,  0, -1,  1, -1,  1,  0],
+    [ 0,  0,  0,  0,  0, -1,  1,  0,  0],
+];
+
+/// bitboard masks of the (up to 8) neighbours of every cell of the board
+const NEIGHBOURS: [u128; CELLS] = neighbour_masks();
+
+const fn neighbour_masks() -> [u128; CELLS] {
+    let mut masks = [0u128; CELLS];
+    let mut c = 0;
+    while c < CELLS {
+        let (i, j) = ((c / SIZE) as isize, (c % SIZE) as isize);
+        let mut di = -1;
+        while di <= 1 {
+            let mut dj = -1;
+            while dj <= 1 {
+                let (ni, nj) = (i + di, j + dj);
+                if (di != 0 || dj != 0) && 0 <= ni && ni < SIZE as isize && 0 <= nj && nj < SIZE as isize {
+                    masks[c] |= 1u128 << ((ni * SIZE as isize + nj) as u32);
+                }
+                dj += 1;
+            }
+            di += 1;
+        }
+        c += 1;
+    }
+    return masks;
+}
+
+/// iterates over the index of every set bit of a bitboard
+fn bits(mut mask: u128) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 { return None; }
+        let c = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        Some(c)
+    })
+}
+
+fn index(pos: Coords) -> usize {
+    assert!(pos.0 < SIZE && pos.1 < SIZE, "Cell outside expected range");
+    return SIZE * pos.0 + pos.1;
+}
+
+fn coords(c: usize) -> Coords { return (c / SIZE, c % SIZE) }
+
+/// State of an Avalam game.
+///
+/// Each cell holds its signed tower height (positive towers are controlled by player 1 and
+/// negative ones by player 2) and the `occupancy` bitboard marks the cells that hold a tower,
+/// so the move generation only needs integer operations. `ratios` counts the pieces of each
+/// color in every tower and `towers` the towers controlled by each player, kept up to date
+/// whenever a cell changes so the score never needs a scan of the board.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct AvalamState {
+    occupancy: u128,
+    cells: [i8; CELLS],
+    ratios: [[u8; CELLS]; 2],
+    towers: (usize, usize),
+    pub turn: u32,
+    pub curr_pid: u32,
+}
+
+impl Default for AvalamState {
+    fn default() -> Self { return Self::new() }
+}
+
+impl AvalamState {
+    /// returns the state of an Avalam game in the initial position
+    pub fn new() -> Self {
+        let mut state = AvalamState {
+            occupancy: 0,
+            cells: [0; CELLS],
+            ratios: [[0; CELLS]; 2],
+            towers: (0, 0),
+            turn: 0,
+            curr_pid: 1,
+        };
+
+        for c in 0..CELLS {
+            let v = INITIAL_BOARD[c / SIZE][c % SIZE];
+            state.set(c, i64::from(v));
+            state.ratios[0][c] = u8::from(v == 1);
+            state.ratios[1][c] = u8::from(v == -1);
+        }
+        return state;
+    }
+
+    /// builds a state from its signed towers and the ratios of every tower, or returns the cell
+    /// breaking the rules and why. Without ratios, every piece of a tower is of its top color.
+    /// The turn is deduced from the number of towers left and the current player from the turn
+    pub fn from_board(board: &[[i64; SIZE]; SIZE], ratios: Option<&[[[i64; SIZE]; SIZE]; 2]>) -> Result<Self, (Coords, String)> {
+        let mut state = AvalamState::new();
+        for (i, row) in board.iter().enumerate() {
+            for (j, &v) in row.iter().enumerate() {
+                let h = v.abs();
+                if h > i64::from(MAX_HEIGHT) {
+                    return Err(((i, j), format!("the tower has height {h}, taller than {MAX_HEIGHT}")));
+                }
+
+                let ratio = match ratios {
+                    Some(r) => [r[0][i][j], r[1][i][j]],
+                    None if v > 0 => [h, 0],
+                    None => [0, h],
+                };
+                if ratio[0] < 0 || ratio[1] < 0 {
+                    return Err(((i, j), format!("the ratios {ratio:?} are negative")));
+                }
+                if ratio[0] + ratio[1] != h {
+                    return Err(((i, j), format!("the ratios {ratio:?} do not sum to the tower height {h}")));
+                }
+
+                state.set_tower((i, j), v);
+                state.set_ratio(0, (i, j), ratio[0]);
+                state.set_ratio(1, (i, j), ratio[1]);
+            }
+        }
+
+        let (p1, p2) = state.score();
+        state.turn = INITIAL_TOWERS.saturating_sub(p1 + p2) as u32;
+        state.curr_pid = (state.turn % 2) + 1;
+        return Ok(state);
+    }
+
+    /// builds a state from all of its fields, or returns why they do not form a valid state. The
+    /// towers are validated by [`AvalamState::from_board`] and [`AvalamState::check_invariants`],
+    /// and the current player must be 1 or 2. Every decoder goes through it, so corrupt input is
+    /// rejected instead of being truncated into towers that break the move generation
+    pub fn from_parts(board: &[[i64; SIZE]; SIZE], ratios: &[[[i64; SIZE]; SIZE]; 2], turn: u32, curr_pid: u32) -> Result<Self, String> {
+        let mut state = Self::from_board(board, Some(ratios))
+            .map_err(|(pos, reason)| format!("invalid cell {pos:?}: {reason}"))?;
+        state.check_invariants()?;
+        if curr_pid != 1 && curr_pid != 2 { return Err(format!("invalid player to move {curr_pid}")); }
+
+        state.turn = turn;
+        state.curr_pid = curr_pid;
+        return Ok(state);
+    }
+
+    /// checks the consistency of the state, to track down bugs in the updates of the board: every
+    /// tower is at most [`MAX_HEIGHT`] tall, its ratios sum to its height and hold at least one
+    /// piece of its top color, and the occupancy and tower counts match the cells. Otherwise, it
+    /// returns the first broken invariant. It is a full scan, meant for tests and debugging
+    pub fn check_invariants(&self) -> Result<(), String> {
+        for c in 0..CELLS {
+            let (pos, v, h) = (coords(c), self.cells[c], self.height(c));
+            let ratio = [self.ratios[0][c], self.ratios[1][c]];
+
+            if h > MAX_HEIGHT {
+                return Err(format!("the tower on {pos:?} has height {h}, taller than {MAX_HEIGHT}"));
+            }
+            if u16::from(ratio[0]) + u16::from(ratio[1]) != u16::from(h) {
+                return Err(format!("the ratios {ratio:?} on {pos:?} do not sum to the tower height {h}"));
+            }
+            if (v > 0 && ratio[0] == 0) || (v < 0 && ratio[1] == 0) {
+                return Err(format!("the ratios {ratio:?} on {pos:?} hold no piece of the top color of {v}"));
+            }
+            if (self.occupancy >> c) & 1 != u128::from(v != 0) {
+                return Err(format!("the occupancy of {pos:?} does not match its tower {v}"));
+            }
+        }
+
+        let towers = self.cells.iter().fold((0, 0), |b, &v| match v.signum() {
+            1 => (b.0 + 1, b.1),
+            -1 => (b.0, b.1 + 1),
+            _ => b,
+        });
+        if towers != self.towers {
+            return Err(format!("the tower counts {:?} do not match the board {towers:?}", self.towers));
+        }
+        return Ok(());
+    }
+
+    fn set(&mut self, c: usize, v: i64) {
+        // a merge empties the origin and can change the controller of the destination
+        match self.cells[c].signum() {
+            1 => self.towers.0 -= 1,
+            -1 => self.towers.1 -= 1,
+            _ => {},
+        }
+        match v.signum() {
+            1 => self.towers.0 += 1,
+            -1 => self.towers.1 += 1,
+            _ => {},
+        }
+
+        self.cells[c] = v as i8;
+        if v != 0 { self.occupancy |= 1u128 << c; } else { self.occupancy &= !(1u128 << c); }
+    }
+
+    fn height(&self, c: usize) -> u8 { return self.cells[c].unsigned_abs() }
+
+    /// returns the signed tower on `pos`
+    pub fn tower(&self, pos: Coords) -> i64 { return i64::from(self.cells[index(pos)]) }
+
+    /// returns the color controlling the tower on `pos`: 1 for player 1, -1 for player 2 and 0 if
+    /// the cell is empty
+    pub fn top_color(&self, pos: Coords) -> i8 { return self.cells[index(pos)].signum() }
+
+    /// replaces the signed tower on `pos`
+    pub fn set_tower(&mut self, pos: Coords, v: i64) { self.set(index(pos), v) }
+
+    /// returns the number of pieces of each color in the tower on `pos`
+    pub fn ratio(&self, pos: Coords) -> [i64; 2] {
+        let c = index(pos);
+        return [i64::from(self.ratios[0][c]), i64::from(self.ratios[1][c])];
+    }
+
+    /// replaces the number of pieces of the given color (0 or 1) in the tower on `pos`
+    pub fn set_ratio(&mut self, color: usize, pos: Coords, v: i64) {
+        self.ratios[color][index(pos)] = v as u8;
+    }
+
+    /// returns the bitboards of the towers by height: the `k`-th one marks the towers at most `k`
+    /// tall, which a tower of height `MAX_HEIGHT - k` can be moved onto. It is built once per
+    /// scan of the board, so the targets of each tower are a single mask
+    fn fitting(&self) -> Fitting {
+        let mut fitting: Fitting = [0; MAX_HEIGHT as usize + 1];
+        for c in bits(self.occupancy) {
+            fitting[usize::from(self.height(c))] |= 1u128 << c;
+        }
+        for k in 1..fitting.len() {
+            fitting[k] |= fitting[k - 1];
+        }
+        return fitting;
+    }
+
+    /// bitboard of the cells the tower on `c` can be moved onto, with the `fitting` bitboards of
+    /// [`AvalamState::fitting`]
+    fn targets(&self, c: usize, fitting: &Fitting) -> u128 {
+        return NEIGHBOURS[c] & fitting[usize::from(MAX_HEIGHT - self.height(c))];
+    }
+
+    /// returns the legal moves of the state. In the case of the Avalam game, both players can
+    /// play the same set of moves
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(INITIAL_MOVES);
+        self.legal_moves_into(&mut moves);
+        return moves;
+    }
+
+    /// writes the legal moves in `buffer`, replacing its content. Reusing the same buffer across
+    /// calls avoids reallocating it on every move generation
+    pub fn legal_moves_into(&self, buffer: &mut Vec<Move>) {
+        buffer.clear();
+        let fitting = self.fitting();
+        buffer.extend(bits(self.occupancy).flat_map(|c| {
+            bits(self.targets(c, &fitting)).map(move |n| (coords(c), coords(n)))
+        }));
+    }
+
+    /// returns the legal moves stacking a neighbouring tower on top of `dest`. As the merged
+    /// tower is the same whichever of the two is moved, these are the targets of `dest` itself.
+    /// It returns an error if `dest` is outside of the board or holds no tower
+    pub fn moves_to(&self, dest: Coords) -> Result<Vec<Move>, &'static str> {
+        if dest.0 >= SIZE || dest.1 >= SIZE { return Err("the destination is outside of the board"); }
+        let d = index(dest);
+        if self.height(d) == 0 { return Err("there is no tower on the destination"); }
+
+        return Ok(bits(self.targets(d, &self.fitting())).map(|n| (coords(n), dest)).collect());
+    }
+
+    /// returns the same moves as [`AvalamState::legal_moves`], in the same order. The rows are
+    /// generated on the rayon thread pool with [`AvalamState::row_parallel_moves`] when at least
+    /// [`PARALLEL_MIN_TOWERS`] towers are left, and serially otherwise.
+    ///
+    /// It is meant for generating the moves of a few large positions on multicore machines. The
+    /// engine itself only uses the serial path, as searches and playouts already run a task per
+    /// state on the thread pool
+    pub fn par_legal_moves(&self) -> Vec<Move> {
+        if self.occupancy.count_ones() < PARALLEL_MIN_TOWERS { return self.legal_moves(); }
+        return self.row_parallel_moves();
+    }
+
+    /// generates the moves of every row of origins as a task of the rayon thread pool, whatever
+    /// the number of towers. A move is only generated from its origin, so the rows give disjoint
+    /// moves and are concatenated without deduplication
+    pub fn row_parallel_moves(&self) -> Vec<Move> {
+        let fitting = self.fitting();
+        let rows: Vec<Vec<Move>> = (0..SIZE).into_par_iter().map(|r| {
+            let origins = self.occupancy & (ROW_MASK << (r * SIZE));
+            return bits(origins).flat_map(|c| {
+                bits(self.targets(c, &fitting)).map(move |n| (coords(c), coords(n)))
+            }).collect();
+        }).collect();
+        return rows.concat();
+    }
+
+    /// returns the number of legal moves, without building them
+    pub fn legal_move_count(&self) -> usize {
+        let fitting = self.fitting();
+        return bits(self.occupancy).map(|c| self.targets(c, &fitting).count_ones() as usize).sum();
+    }
+
+    /// returns the mobility of the position, its number of legal moves, as an evaluation feature.
+    /// It is [`AvalamState::legal_move_count`]: the moves are counted from the bitboards, without
+    /// allocating
+    pub fn mobility(&self) -> usize { return self.legal_move_count() }
+
+    /// returns the number of legal moves moving a tower topped by player 1 and by player 2. Their
+    /// sum is the [`AvalamState::mobility`], and it does not allocate either
+    pub fn mobility_by_player(&self) -> (usize, usize) {
+        let fitting = self.fitting();
+        return bits(self.occupancy).fold((0, 0), |b, c| {
+            let moves = self.targets(c, &fitting).count_ones() as usize;
+            if self.cells[c] > 0 { return (b.0 + moves, b.1); }
+            return (b.0, b.1 + moves);
+        });
+    }
+
+    /// checks that a move is legal: both cells hold a tower, they are neighbours and the merged
+    /// tower is not taller than [`MAX_HEIGHT`]. Otherwise, it returns the reason it is illegal
+    pub fn check_move(&self, c_move: Move) -> Result<(), &'static str> {
+        let ((oi, oj), (di, dj)) = c_move;
+        if oi >= SIZE || oj >= SIZE || di >= SIZE || dj >= SIZE { return Err("the move is outside of the board"); }
+
+        let (origin, dest) = (index(c_move.0), index(c_move.1));
+        if self.height(origin) == 0 { return Err("there is no tower on the origin"); }
+        if self.height(dest) == 0 { return Err("there is no tower on the destination"); }
+        if NEIGHBOURS[origin] & (1u128 << dest) == 0 { return Err("the cells are not neighbours"); }
+        if self.height(origin) + self.height(dest) > MAX_HEIGHT { return Err("the merged tower would be too tall"); }
+        return Ok(());
+    }
+
+    /// returns whether a move is legal, as checked by [`AvalamState::check_move`]
+    pub fn is_legal(&self, c_move: Move) -> bool { return self.check_move(c_move).is_ok() }
+
+    /// returns whether any move can still be played
+    pub fn has_moves(&self) -> bool {
+        let fitting = self.fitting();
+        return bits(self.occupancy).any(|c| self.targets(c, &fitting) != 0);
+    }
+
+    /// returns the number of towers of each player that can never be merged again, as none of
+    /// their neighbours can be stacked with them. Towers only grow and cells never refill, so
+    /// these towers are guaranteed points for their owner
+    pub fn stable_towers(&self) -> (usize, usize) {
+        return self.count_towers(|targets| targets == 0);
+    }
+
+    /// returns the number of towers of each player that can be moved by at least one legal move
+    pub fn movable_count(&self) -> (usize, usize) {
+        return self.count_towers(|targets| targets != 0);
+    }
+
+    /// counts the towers of each player whose bitboard of targets satisfies `condition`
+    fn count_towers(&self, condition: impl Fn(u128) -> bool) -> (usize, usize) {
+        let fitting = self.fitting();
+        return bits(self.occupancy).filter(|&c| condition(self.targets(c, &fitting))).fold((0, 0), |b, c| {
+            if self.cells[c] > 0 { return (b.0 + 1, b.1); }
+            return (b.0, b.1 + 1);
+        });
+    }
+
+    /// plays a move and returns the following state. The move is not validated
+    pub fn play(&self, c_move: Move) -> Self {
+        let mut new_state = self.clone();
+        new_state.play_in_place(c_move);
+        return new_state;
+    }
+
+    /// plays a move on this state, moving the tower on the origin on top of the destination
+    pub fn play_in_place(&mut self, c_move: Move) {
+        let origin = index(c_move.0);
+        let dest = index(c_move.1);
+
+        let top = i64::from(self.cells[origin]);
+        let bottom = i64::from(self.cells[dest]);
+
+        self.set(origin, 0);
+        self.set(dest, top.signum() * bottom.abs() + top);
+
+        for ratio in self.ratios.iter_mut() {
+            ratio[dest] += ratio[origin];
+            ratio[origin] = 0;
+        }
+
+        self.turn += 1;
+        self.curr_pid = (self.curr_pid % 2) + 1;
+    }
+
+    /// returns the state with its board and ratios mapped by one of the [`SYMMETRIES`], as given
+    /// by [`symmetry`]. The rules do not depend on the orientation of the board, so both states
+    /// have the same outcome
+    pub fn transformed(&self, sym: usize) -> Self {
+        let mut state = AvalamState {
+            occupancy: 0,
+            cells: [0; CELLS],
+            ratios: [[0; CELLS]; 2],
+            towers: (0, 0),
+            turn: self.turn,
+            curr_pid: self.curr_pid,
+        };
+
+        for (c, &v) in self.cells.iter().enumerate() {
+            let t = index(symmetry(sym, coords(c), SIZE));
+            state.set(t, i64::from(v));
+            state.ratios[0][t] = self.ratios[0][c];
+            state.ratios[1][t] = self.ratios[1][c];
+        }
+        return state;
+    }
+
+    /// returns whether `other` is this state under one of the [`SYMMETRIES`], comparing the
+    /// towers, their ratios, the turn and the player to move
+    pub fn is_symmetric_to(&self, other: &AvalamState) -> bool {
+        return (0..SYMMETRIES).any(|sym| self.transformed(sym) == *other);
+    }
+
+    /// returns a compact byte encoding of the position: the signed tower of every cell followed by
+    /// the current player. The ratios and turn are left out as they do not change the outcome of
+    /// the game, and the legal moves are fully defined by the towers
+    pub fn fingerprint(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(CELLS + 1);
+        bytes.extend(self.cells.iter().map(|&v| v as u8));
+        bytes.push(self.curr_pid as u8);
+        return bytes;
+    }
+
+    /// returns a textual encoding of the state, read back with [`AvalamState::from_fen`]. It is
+    /// made of 3 fields separated by spaces:
+    /// - the 9 rows of the board from row 0, separated by `/`. A tower is written as its top
+    ///   color, `+` for player 1 or `-` for player 2, followed by the number of pieces of player 1
+    ///   and of player 2 it holds, and a run of `n` empty cells as the digit `n`
+    /// - the player to move, `1` or `2`
+    /// - the turn
+    ///
+    /// For example, the first row of the initial board is `2+10-015`. Runs of empty cells are
+    /// always merged, so equal states have the same encoding
+    pub fn to_fen(&self) -> String {
+        let rows = (0..SIZE).map(|i| {
+            let mut row = String::new();
+            let mut empty = 0;
+            for j in 0..SIZE {
+                let c = index((i, j));
+                if self.cells[c] == 0 { empty += 1; continue; }
+                if empty > 0 { row.push_str(&empty.to_string()); }
+                empty = 0;
+
+                let top = if self.cells[c] > 0 { '+' } else { '-' };
+                row.push_str(&format!("{top}{}{}", self.ratios[0][c], self.ratios[1][c]));
+            }
+            if empty > 0 { row.push_str(&empty.to_string()); }
+            return row;
+        }).collect::<Vec<_>>();
+
+        return format!("{} {} {}", rows.join("/"), self.curr_pid, self.turn);
+    }
+
+    /// decodes a state encoded by [`AvalamState::to_fen`], or returns why the encoding is invalid.
+    /// The towers are validated as by [`AvalamState::check_invariants`]
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let [rows, curr_pid, turn] = fields[..] else {
+            return Err(format!("expected 3 fields separated by spaces, got {}", fields.len()));
+        };
+
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() != SIZE { return Err(format!("expected {SIZE} rows, got {}", rows.len())); }
+
+        let mut board = [[0i64; SIZE]; SIZE];
+        let mut ratios = [[[0i64; SIZE]; SIZE]; 2];
+        for (i, row) in rows.iter().enumerate() {
+            let mut j = 0;
+            let mut chars = row.chars();
+            while let Some(ch) = chars.next() {
+                match ch {
+                    '1'..='9' => { j += ch as usize - '0' as usize; },
+                    '+' | '-' => {
+                        let mut count = || chars.next().and_then(|d| d.to_digit(10)).map(i64::from);
+                        let (Some(p1), Some(p2)) = (count(), count()) else {
+                            return Err(format!("row {i}: expected the piece counts of both players after {ch:?}"));
+                        };
+                        if j >= SIZE { return Err(format!("row {i} has more than {SIZE} cells")); }
+                        if p1 + p2 == 0 { return Err(format!("row {i}: the tower on column {j} has no piece")); }
+
+                        board[i][j] = if ch == '+' { p1 + p2 } else { -(p1 + p2) };
+                        ratios[0][i][j] = p1;
+                        ratios[1][i][j] = p2;
+                        j += 1;
+                    },
+                    _ => { return Err(format!("row {i}: unexpected character {ch:?}")); },
+                }
+            }
+            if j != SIZE { return Err(format!("row {i} has {j} cells instead of {SIZE}")); }
+        }
+
+        let curr_pid = match curr_pid {
+            "1" => 1,
+            "2" => 2,
+            _ => return Err(format!("invalid player to move {curr_pid:?}")),
+        };
+        let turn = turn.parse().map_err(|_| format!("invalid turn {turn:?}"))?;
+        return Self::from_parts(&board, &ratios, turn, curr_pid);
+    }
+
+    /// returns the fixed-width encoding of the whole state: the signed towers, the two ratio
+    /// tables, the turn (little endian) and the current player
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RECORD_SIZE);
+        bytes.extend(self.cells.iter().map(|&v| v as u8));
+        bytes.extend(self.ratios.iter().flatten());
+        bytes.extend(self.turn.to_le_bytes());
+        bytes.push(self.curr_pid as u8);
+        return bytes;
+    }
+
+    /// decodes a state encoded by [`AvalamState::to_bytes`]. It returns the reason the record is
+    /// invalid if `bytes` does not have the size of a record or decodes to an impossible state
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != RECORD_SIZE {
+            return Err(format!("expected {RECORD_SIZE} bytes, got {}", bytes.len()));
+        }
+
+        let board = std::array::from_fn(|i| std::array::from_fn(|j| i64::from(bytes[i * SIZE + j] as i8)));
+        let ratios = std::array::from_fn(|p| std::array::from_fn(|i| std::array::from_fn(|j| {
+            i64::from(bytes[(p + 1) * CELLS + i * SIZE + j])
+        })));
+        let turn = &bytes[3 * CELLS..3 * CELLS + 4];
+        let turn = u32::from_le_bytes([turn[0], turn[1], turn[2], turn[3]]);
+        return Self::from_parts(&board, &ratios, turn, u32::from(bytes[3 * CELLS + 4]));
+    }
+
+    /// returns the current score of the state, the number of towers controlled by each player
+    pub fn score(&self) -> (usize, usize) {
+        return self.towers;
+    }
+
+    /// returns the score of player 1 minus the score of player 2
+    pub fn score_difference(&self) -> i64 {
+        let (p1, p2) = self.score();
+        return p1 as i64 - p2 as i64;
+    }
+
+    /// returns the current winner of the game.
+    ///
+    /// If the game is unfinished, it returns 0
+    ///
+    /// If the game is a tie it returns -1
+    ///
+    /// Otherwise, it returns the player id of the winner
+    pub fn winner(&self) -> i64 {
+        // unfinished
+        if self.has_moves() { return 0; }
+
+        let (p1, p2) = self.score();
+        // tie
+        if p1 == p2 { return -1 }
+        // winner
+        return i64::from(p1 < p2) + 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AvalamState;
+    use crate::Core::perft::perft;
+
+    #[test]
+    fn perft_from_the_initial_board() {
+        let state = AvalamState::new();
+        assert_eq!(perft(&state, 1), 292);
+        assert_eq!(perft(&state, 2), 81_488);
+        assert_eq!(perft(&state, 3), 21_711_440);
+    }
+
+    #[test]
+    fn parallel_moves_match_the_serial_ones() {
+        let mut state = AvalamState::new();
+        while state.has_moves() {
+            assert_eq!(state.row_parallel_moves(), state.legal_moves());
+            assert_eq!(state.par_legal_moves(), state.legal_moves());
+            state = state.play(state.legal_moves()[0]);
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! The rules of the Ultimate Tic-tac-toe game
+use itertools::{Itertools};
+use crate::Core::{symmetry, Move, SYMMETRIES};
+
+/// size of the fixed-width encoding given by [`UltiTTTState::to_bytes`]
+pub const RECORD_SIZE: usize = 96;
+
+/// the cells of the rows, columns and diagonals of a tic-tac-toe grid
+const LINES: [[usize; 3]; 8] = [
+    [0, 1, 2], [3, 4, 5], [6, 7, 8],
+    [0, 3, 6], [1, 4, 7], [2, 5, 8],
+    [0, 4, 8], [2, 4, 6],
+];
+
+/// the lines of [`LINES`] as masks of the 9 cells of a grid, in the order [`get_winner_of`]
+/// checks them: diagonals, rows, then columns
+const LINE_MASKS: [u16; 8] = [0x111, 0x054, 0x007, 0x038, 0x1C0, 0x049, 0x092, 0x124];
+/// mask of a full grid
+const FULL_MASK: u16 = 0x1FF;
+
+/// State of an Ultimate Tic-tac-toe game.
+///
+/// Each row of `board` is one of the 9 sub-boards and holds the id of the player occupying each
+/// of its cells (0 if empty). `win_state` is the winner of each sub-board as given by
+/// [`get_winner_of`] and `active_cell` the sub-board the next move must be played in, or -1
+/// if the next move is free. The winner of the game is cached in `meta_winner`, updated with
+/// `win_state`, so checking for the end of the game never recomputes it.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct UltiTTTState {
+    pub board: [[i64; 9]; 9],
+    win_state: [i64; 9],
+    meta_winner: i64,
+    pub active_cell: i64,
+    pub turn: u32,
+    pub curr_pid: u32,
+}
+
+impl Default for UltiTTTState {
+    fn default() -> Self { return Self::new() }
+}
+
+impl UltiTTTState {
+    /// dimensions of the board: a row for each of the 9 sub-boards, holding its 9 cells
+    pub const DIMENSIONS: (usize, usize) = (9, 9);
+
+    /// returns the state of an UltiTTT game in the initial position
+    pub fn new() -> Self {
+        return UltiTTTState {
+            board: [[0; 9]; 9],
+            win_state: [0; 9],
+            meta_winner: 0,
+            active_cell: -1,
+            turn: 0,
+            curr_pid: 1,
+        };
+    }
+
+    /// plays a move and returns the following state. The move is not validated
+    pub fn play(&self, c_move: Move) -> Self {
+        let sup_cell = c_move.0;
+        let sub_cell = c_move.1;
+
+        let sup_i = 3 * sup_cell.0 + sup_cell.1;
+        let sub_i = 3 * sub_cell.0 + sub_cell.1;
+
+        let mut new_state = self.clone();
+        new_state.board[sup_i][sub_i] = i64::from(self.curr_pid);
+        new_state.win_state[sup_i] = get_winner_of(&new_state.board[sup_i]);
+        new_state.meta_winner = get_winner_of(&new_state.win_state);
+
+        new_state.turn += 1;
+        new_state.active_cell =
+            if new_state.win_state[sub_i] != 0 { -1 }
+            else { i64::try_from(sub_i).expect("Cell outside expected range") };
+
+        new_state.curr_pid = (self.curr_pid % 2) + 1;
+        return new_state;
+    }
+
+    /// returns the sub-board the next move must be played in, or None if the next move is free:
+    /// when `active_cell` is -1 or its sub-board is already decided. An `active_cell` outside of
+    /// the board is rejected by [`UltiTTTState::check_invariants`] wherever a state is decoded; it
+    /// is read as a free move here rather than panicking
+    fn forced_sub_board(&self) -> Option<usize> {
+        if self.active_cell == -1 { return None; }
+
+        return usize::try_from(self.active_cell).ok().filter(|&active| self.win_state.get(active) == Some(&0));
+    }
+
+    /// checks that every field of the state is in range: the cells hold 0 or a player id, the won
+    /// sub-boards a player id, 0 or -1, the active cell a sub-board from 0 to 8 or -1 and the
+    /// player to move is 1 or 2. Otherwise, it returns the first field out of range. The states
+    /// decoded from python or from bytes are checked with it, as the rules index the board with
+    /// these values
+    pub fn check_invariants(&self) -> Result<(), String> {
+        for (i, cells) in self.board.iter().enumerate() {
+            if let Some(k) = cells.iter().position(|v| !(0..=2).contains(v)) {
+                return Err(format!("the cell {k} of the sub-board {i} holds {}, not 0, 1 or 2", cells[k]));
+            }
+        }
+        if let Some(i) = self.win_state.iter().position(|v| !(-1..=2).contains(v)) {
+            return Err(format!("the winner {} of the sub-board {i} is not -1, 0, 1 or 2", self.win_state[i]));
+        }
+        if !(-1..=8).contains(&self.active_cell) {
+            return Err(format!("the active cell {} is not a sub-board from 0 to 8 or -1", self.active_cell));
+        }
+        if self.curr_pid != 1 && self.curr_pid != 2 {
+            return Err(format!("invalid player to move {}", self.curr_pid));
+        }
+        return Ok(());
+    }
+
+    /// builds the state of a board alone: the won sub-boards are recomputed from it, the turn is
+    /// the number of filled cells, the current player is deduced from the turn and the next move
+    /// is free. It returns an error if a cell is not 0, 1 or 2, or if the piece counts cannot
+    /// happen in a game, since player 1 starts and has as many pieces as player 2 or one more
+    pub fn from_board(board: &[[i64; 9]; 9]) -> Result<Self, String> {
+        let mut state = UltiTTTState::new();
+        state.board = *board;
+        state.check_invariants()?;
+
+        let count = |player: i64| board.iter().flatten().filter(|&&v| v == player).count();
+        let (p1, p2) = (count(1), count(2));
+        if p1 != p2 && p1 != p2 + 1 {
+            return Err(format!("player 1 has {p1} pieces and player 2 has {p2}, which cannot happen in a game"));
+        }
+
+        state.set_win_state(sub_board_winners(board));
+        state.turn = (p1 + p2) as u32;
+        state.curr_pid = (state.turn % 2) + 1;
+        return Ok(state);
+    }
+
+    /// returns the legal moves of the current player
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let forced = self.forced_sub_board();
+
+        return (0..9).cartesian_product(0..9).filter_map(|(i, j)| {
+            let playable = match forced {
+                Some(active) => i == active,
+                None => self.win_state[i] == 0,
+            };
+            if playable && self.board[i][j] == 0 { Some(((i / 3, i % 3), (j / 3, j % 3))) } else { None }
+        }).collect_vec();
+    }
+
+    /// returns the winner of each sub-board
+    pub fn win_state(&self) -> [i64; 9] { return self.win_state }
+
+    /// replaces the winners of the sub-boards and updates the winner of the game from them
+    pub fn set_win_state(&mut self, win_state: [i64; 9]) {
+        self.win_state = win_state;
+        self.meta_winner = get_winner_of(&win_state);
+    }
+
+    /// returns the number of legal moves of the current player, without building them
+    pub fn legal_move_count(&self) -> usize {
+        return (0..9).cartesian_product(0..9)
+            .filter(|&(i, j)| self.is_legal(((i / 3, i % 3), (j / 3, j % 3))))
+            .count();
+    }
+
+    /// checks that a move is legal: its cell is empty, its sub-board is undecided and it is the
+    /// forced sub-board if there is one. Otherwise, it returns the reason it is illegal
+    pub fn check_move(&self, c_move: Move) -> Result<(), &'static str> {
+        let (sup_cell, sub_cell) = c_move;
+        if sup_cell.0 >= 3 || sup_cell.1 >= 3 || sub_cell.0 >= 3 || sub_cell.1 >= 3 {
+            return Err("the move is outside of the board");
+        }
+
+        let sup_i = 3 * sup_cell.0 + sup_cell.1;
+        let sub_i = 3 * sub_cell.0 + sub_cell.1;
+        if self.board[sup_i][sub_i] != 0 { return Err("the cell is not empty"); }
+        if self.win_state[sup_i] != 0 { return Err("the sub-board is already decided"); }
+
+        return match self.forced_sub_board() {
+            Some(active) if sup_i != active => Err("the move must be played in the forced sub-board"),
+            _ => Ok(()),
+        }
+    }
+
+    /// returns whether a move is legal, as checked by [`UltiTTTState::check_move`]
+    pub fn is_legal(&self, c_move: Move) -> bool { return self.check_move(c_move).is_ok() }
+
+    /// returns the number of cells of player 1, of player 2 and of empty cells in the sub-board
+    /// `idx`, in row order. It panics if `idx` is not below 9
+    pub fn sub_board_counts(&self, idx: usize) -> (usize, usize, usize) {
+        return self.board[idx].iter().fold((0, 0, 0), |b, &v| match v {
+            1 => (b.0 + 1, b.1, b.2),
+            2 => (b.0, b.1 + 1, b.2),
+            _ => (b.0, b.1, b.2 + 1),
+        });
+    }
+
+    /// returns the number of empty cells of each sub-board that can still be played, as an
+    /// evaluation feature: it is 0 for the decided sub-boards. It is computed in a single pass
+    /// over the board, whichever sub-board is forced
+    pub fn sub_board_mobility(&self) -> [usize; 9] {
+        return std::array::from_fn(|i| {
+            if self.win_state[i] != 0 { return 0; }
+            return self.board[i].iter().filter(|&&v| v == 0).count();
+        });
+    }
+
+    /// returns the state with its board mapped by one of the [`SYMMETRIES`], as given by
+    /// [`symmetry`]. The same symmetry is applied to the sub-boards and to the cells inside of
+    /// them, which maps the board as a whole, and the active cell follows its sub-board
+    pub fn transformed(&self, sym: usize) -> Self {
+        let map = |i: usize| -> usize {
+            let (r, c) = symmetry(sym, (i / 3, i % 3), 3);
+            return 3 * r + c;
+        };
+
+        let mut state = self.clone();
+        for sup_i in 0..9 {
+            state.win_state[map(sup_i)] = self.win_state[sup_i];
+            for sub_i in 0..9 {
+                state.board[map(sup_i)][map(sub_i)] = self.board[sup_i][sub_i];
+            }
+        }
+        if let Ok(active) = usize::try_from(self.active_cell) {
+            state.active_cell = map(active) as i64;
+        }
+        return state;
+    }
+
+    /// returns whether `other` is this state under one of the [`SYMMETRIES`], comparing the
+    /// board, the active cell, the turn and the player to move
+    pub fn is_symmetric_to(&self, other: &UltiTTTState) -> bool {
+        return (0..SYMMETRIES).any(|sym| self.transformed(sym) == *other);
+    }
+
+    /// returns a compact byte encoding of the position: the 81 cells, the active cell and the
+    /// current player. The won sub-boards are left out as they are defined by the cells
+    pub fn fingerprint(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(83);
+        bytes.extend(self.board.iter().flatten().map(|&v| v as u8));
+        bytes.push(self.active_cell as u8);
+        bytes.push(self.curr_pid as u8);
+        return bytes;
+    }
+
+    /// returns a textual encoding of the state, read back with [`UltiTTTState::from_fen`]. It is
+    /// made of 4 fields separated by spaces:
+    /// - the 9 sub-boards in row order, separated by `/`. Each one lists its cells in row order,
+    ///   `X` for player 1, `O` for player 2 and the digit `n` for a run of `n` empty cells
+    /// - the forced sub-board, from 0 to 8, or `-` if the next move is free
+    /// - the player to move, `1` or `2`
+    /// - the turn
+    ///
+    /// For example, the initial state is `9/9/9/9/9/9/9/9/9 - 1 0`. The won sub-boards are left
+    /// out as they are defined by the cells. Runs of empty cells are always merged, so equal
+    /// states have the same encoding
+    pub fn to_fen(&self) -> String {
+        let sub_boards = self.board.iter().map(|cells| {
+            let mut sub_board = String::new();
+            let mut empty = 0;
+            for &v in cells {
+                if v == 0 { empty += 1; continue; }
+                if empty > 0 { sub_board.push_str(&empty.to_string()); }
+                empty = 0;
+                sub_board.push(if v == 1 { 'X' } else { 'O' });
+            }
+            if empty > 0 { sub_board.push_str(&empty.to_string()); }
+            return sub_board;
+        }).collect::<Vec<_>>();
+
+        let active = if self.active_cell == -1 { "-".to_string() } else { self.active_cell.to_string() };
+        return format!("{} {} {} {}", sub_boards.join("/"), active, self.curr_pid, self.turn);
+    }
+
+    /// decodes a state encoded by [`UltiTTTState::to_fen`], or returns why the encoding is
+    /// invalid. The won sub-boards are recomputed from the cells
+    pub fn from_fen(fen: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let [sub_boards, active, curr_pid, turn] = fields[..] else {
+            return Err(format!("expected 4 fields separated by spaces, got {}", fields.len()));
+        };
+
+        let sub_boards: Vec<&str> = sub_boards.split('/').collect();
+        if sub_boards.len() != 9 { return Err(format!("expected 9 sub-boards, got {}", sub_boards.len())); }
+
+        let mut state = UltiTTTState::new();
+        for (i, sub_board) in sub_boards.iter().enumerate() {
+            let mut k = 0;
+            for ch in sub_board.chars() {
+                let (v, n) = match ch {
+                    'X' => (1, 1),
+                    'O' => (2, 1),
+                    '1'..='9' => (0, ch as usize - '0' as usize),
+                    _ => return Err(format!("sub-board {i}: unexpected character {ch:?}")),
+                };
+                if k + n > 9 { return Err(format!("sub-board {i} has more than 9 cells")); }
+                state.board[i][k..k + n].fill(v);
+                k += n;
+            }
+            if k != 9 { return Err(format!("sub-board {i} has {k} cells instead of 9")); }
+        }
+        state.set_win_state(sub_board_winners(&state.board));
+
+        state.active_cell = match active {
+            "-" => -1,
+            _ => match active.parse() {
+                Ok(cell @ 0..=8) => cell,
+                _ => return Err(format!("invalid forced sub-board {active:?}")),
+            },
+        };
+        state.curr_pid = match curr_pid {
+            "1" => 1,
+            "2" => 2,
+            _ => return Err(format!("invalid player to move {curr_pid:?}")),
+        };
+        state.turn = turn.parse().map_err(|_| format!("invalid turn {turn:?}"))?;
+        return Ok(state);
+    }
+
+    /// returns the fixed-width encoding of the whole state: the 81 cells, the won sub-boards, the
+    /// active cell, the turn (little endian) and the current player
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(RECORD_SIZE);
+        bytes.extend(self.board.iter().flatten().map(|&v| v as u8));
+        bytes.extend(self.win_state.iter().map(|&v| v as u8));
+        bytes.push(self.active_cell as u8);
+        bytes.extend(self.turn.to_le_bytes());
+        bytes.push(self.curr_pid as u8);
+        return bytes;
+    }
+
+    /// decodes a state encoded by [`UltiTTTState::to_bytes`]. It returns the reason the record is
+    /// invalid if `bytes` does not have the size of a record or decodes to an impossible state
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != RECORD_SIZE {
+            return Err(format!("expected {RECORD_SIZE} bytes, got {}", bytes.len()));
+        }
+
+        let mut state = UltiTTTState::new();
+        for (c, &v) in bytes[..81].iter().enumerate() {
+            state.board[c / 9][c % 9] = i64::from(v as i8);
+        }
+        state.set_win_state(std::array::from_fn(|i| i64::from(bytes[81 + i] as i8)));
+        state.active_cell = i64::from(bytes[90] as i8);
+        state.turn = u32::from_le_bytes([bytes[91], bytes[92], bytes[93], bytes[94]]);
+        state.curr_pid = u32::from(bytes[95]);
+        state.check_invariants()?;
+        return Ok(state);
+    }
+
+    /// returns the current score of the state. In the case of UltiTTT, it is 1 for the winner
+    /// once the game is won and 0 otherwise
+    pub fn score(&self) -> (usize, usize) {
+        return match self.winner() {
+            1 => (1, 0),
+            2 => (0, 1),
+            _ => (0, 0),
+        };
+    }
+
+    /// returns the score of player 1 minus the score of player 2
+    pub fn score_difference(&self) -> i64 {
+        let (p1, p2) = self.score();
+        return p1 as i64 - p2 as i64;
+    }
+
+    /// returns whether `player` won three sub-boards in a line of the meta-board. It is false for
+    /// anything but the player ids 1 and 2, as undecided and tied sub-boards are no player's
+    pub fn win_for(&self, player: i64) -> bool {
+        if player != 1 && player != 2 { return false; }
+        return LINES.iter().any(|line| line.iter().all(|&k| self.win_state[k] == player));
+    }
+
+    /// returns whether the game is a draw: every sub-board is decided and no player won three of
+    /// them in a line. Unlike a `winner` of -1, it never holds for a game that has a winner
+    pub fn is_draw(&self) -> bool {
+        return !self.win_state.contains(&0) && !self.win_for(1) && !self.win_for(2);
+    }
+
+    /// returns the current winner of the game.
+    ///
+    /// If the game is unfinished, it returns 0
+    ///
+    /// If the game is a tie it returns -1
+    ///
+    /// Otherwise, it returns the player id of the winner
+    pub fn winner(&self) -> i64 {
+        return self.meta_winner
+    }
+}
+
+/// returns the winner of each of the 9 sub-boards of `board`, as [`get_winner_of`] would for
+/// every row, for boards holding player ids 1 and 2. The cells of every player are gathered in
+/// bitboards in a single pass over the board, which are then matched against [`LINE_MASKS`], so
+/// it is faster than calling [`get_winner_of`] 9 times when building states in bulk
+pub fn sub_board_winners(board: &[[i64; 9]; 9]) -> [i64; 9] {
+    // the cells of player 1, of player 2 and the filled cells of every sub-board
+    let mut masks = [[0u16; 3]; 9];
+    for (sub_masks, row) in masks.iter_mut().zip(board) {
+        for (k, &v) in row.iter().enumerate() {
+            if v != 0 { sub_masks[2] |= 1 << k; }
+            match v {
+                1 => sub_masks[0] |= 1 << k,
+                2 => sub_masks[1] |= 1 << k,
+                _ => {},
+            }
+        }
+    }
+
+    return std::array::from_fn(|i| {
+        let [p1, p2, filled] = masks[i];
+        if filled == FULL_MASK { return -1; }
+
+        return LINE_MASKS.iter().find_map(|&line| {
+            if p1 & line == line { return Some(1); }
+            if p2 & line == line { return Some(2); }
+            return None;
+        }).unwrap_or(0);
+    });
+}
+
+/// returns the winner of a tic-tac-toe grid given as its 9 cells in row order. It is 0 if the
+/// grid is undecided, -1 if it is full and the player id of the winner otherwise
+pub fn get_winner_of(g: &[i64; 9]) -> i64 {
+    if !g.contains(&0) {
+        return -1
+    }
+
+    let win_con = [
+        // diagonals
+        [g[0], g[4], g[8]],
+        [g[2], g[4], g[6]],
+        // rows
+        [g[0], g[1], g[2]],
+        [g[3], g[4], g[5]],
+        [g[6], g[7], g[8]],
+        // cols
+        [g[0], g[3], g[6]],
+        [g[1], g[4], g[7]],
+        [g[2], g[5], g[8]],
+    ];
+
+    let result = win_con.iter().find(|v| {
+        let val = v.iter().fold(-1, |a: i64, &v| {
+            return if a == -1 { v } else if a == v { a } else { 0 }
+        });
+        return ![0, -1].contains(&val)
+    });
+
+    return match result {
+        None => { 0 }
+        Some(v) => { v[0] }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UltiTTTState;
+    use crate::Core::perft::perft;
+
+    #[test]
+    fn perft_from_the_initial_board() {
+        let state = UltiTTTState::new();
+        assert_eq!(perft(&state, 1), 81);
+        assert_eq!(perft(&state, 2), 720);
+        assert_eq!(perft(&state, 3), 6_336);
+        assert_eq!(perft(&state, 4), 55_080);
+    }
+
+    #[test]
+    fn only_players_win() {
+        let mut state = UltiTTTState::new();
+        assert!(!state.win_for(0));
+
+        state.set_win_state([-1, 0, 0, -1, 0, 0, -1, 0, 0]);
+        assert!(!state.win_for(-1));
+        assert!(!state.win_for(1) && !state.win_for(2));
+    }
+
+    #[test]
+    fn from_board_rebuilds_the_state() {
+        let mut board = [[0; 9]; 9];
+        board[0][..3].copy_from_slice(&[1, 1, 1]);
+        board[4][..2].copy_from_slice(&[2, 2]);
+        let state = UltiTTTState::from_board(&board).unwrap();
+        assert_eq!(state.win_state(), [1, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!((state.turn, state.curr_pid, state.active_cell), (5, 2, -1));
+
+        board[4][2] = 2;
+        assert!(UltiTTTState::from_board(&board).is_ok());
+        board[4][3] = 2;
+        assert!(UltiTTTState::from_board(&board).is_err());
+        board[4][3] = 3;
+        assert!(UltiTTTState::from_board(&board).is_err());
+    }
+}
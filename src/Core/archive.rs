@@ -0,0 +1,112 @@
+//! Archives packing many states of a game in a single file, with one fixed-width record per
+//! state so any of them can be read by index.
+//!
+//! The file starts with a 16 bytes header: the `GEAR` magic, the format version (u16), the game
+//! (u8), a padding byte and the number of records (u64), all little endian. The records follow,
+//! each one encoded by the `to_bytes` method of the state.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use memmap2::Mmap;
+use crate::Core::{AnyState, Game};
+
+const MAGIC: &[u8; 4] = b"GEAR";
+const VERSION: u16 = 1;
+const HEADER_SIZE: usize = 16;
+
+fn game_id(game: Game) -> u8 {
+    return match game {
+        Game::Avalam => 0,
+        Game::UltiTTT => 1,
+    }
+}
+
+fn game_of(id: u8) -> Option<Game> {
+    return match id {
+        0 => Some(Game::Avalam),
+        1 => Some(Game::UltiTTT),
+        _ => None,
+    }
+}
+
+fn invalid(msg: String) -> io::Error {
+    return io::Error::new(io::ErrorKind::InvalidData, msg);
+}
+
+/// writes the states in an archive at `path`. Every state must belong to `game`
+pub fn write_archive<P: AsRef<Path>>(path: P, game: Game, states: &[AnyState]) -> io::Result<()> {
+    if let Some(i) = states.iter().position(|s| s.game() != game) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput, format!("state {i} is not a state of {game:?}")
+        ));
+    }
+
+    let mut file = BufWriter::new(File::create(path)?);
+    file.write_all(MAGIC)?;
+    file.write_all(&VERSION.to_le_bytes())?;
+    file.write_all(&[game_id(game), 0])?;
+    file.write_all(&(states.len() as u64).to_le_bytes())?;
+
+    for state in states {
+        file.write_all(&state.to_bytes())?;
+    }
+    return file.flush();
+}
+
+/// A memory mapped archive. Records are only decoded when accessed
+pub struct Archive {
+    mmap: Mmap,
+    game: Game,
+    len: usize,
+}
+
+impl Archive {
+    /// maps the archive at `path` in memory, checking its header and size
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        if mmap.len() < HEADER_SIZE || &mmap[0..4] != MAGIC {
+            return Err(invalid("not a state archive".to_string()));
+        }
+
+        let version = u16::from_le_bytes([mmap[4], mmap[5]]);
+        if version != VERSION {
+            return Err(invalid(format!("unsupported archive version {version}, expected {VERSION}")));
+        }
+
+        let game = game_of(mmap[6]).ok_or_else(|| invalid(format!("unknown game id {}", mmap[6])))?;
+        let len = u64::from_le_bytes(mmap[8..16].try_into().unwrap()) as usize;
+
+        let expected = len.checked_mul(game.record_size()).and_then(|n| n.checked_add(HEADER_SIZE));
+        if expected != Some(mmap.len()) {
+            return Err(invalid(format!("truncated archive, expected {len} records")));
+        }
+
+        return Ok(Archive { mmap, game, len });
+    }
+
+    /// the game of the archived states
+    pub fn game(&self) -> Game { return self.game }
+
+    /// the number of archived states
+    pub fn len(&self) -> usize { return self.len }
+
+    pub fn is_empty(&self) -> bool { return self.len == 0 }
+
+    /// decodes the state at `index`, or returns None if it is out of the archive. A corrupt
+    /// record gives the reason it could not be decoded
+    pub fn get(&self, index: usize) -> Option<Result<AnyState, String>> {
+        if index >= self.len { return None; }
+
+        let size = self.game.record_size();
+        let start = HEADER_SIZE + index * size;
+        return Some(AnyState::from_bytes(self.game, &self.mmap[start..start + size])
+            .map_err(|reason| format!("corrupt record {index}: {reason}")));
+    }
+
+    /// iterates over the archived states, in order
+    pub fn iter(&self) -> impl Iterator<Item = Result<AnyState, String>> + '_ {
+        return (0..self.len).filter_map(|i| self.get(i));
+    }
+}
@@ -0,0 +1,229 @@
+//! The game rules implemented in plain rust, without any python binding. With the `python`
+//! feature disabled, this is all the crate exposes
+pub mod Avalam;
+pub mod UltiTTT;
+pub mod playout;
+pub mod solve;
+pub mod perft;
+pub mod transposition;
+pub mod archive;
+
+use crate::Core::Avalam::AvalamState;
+use crate::Core::UltiTTT::UltiTTTState;
+
+/// a position on a board, as (row, column)
+pub type Coords = (usize, usize);
+/// a move of a game. For Avalam, it is the (origin, destination) of the moved tower and for
+/// UltiTTT, the (sub-board, cell) where the piece is placed
+pub type Move = (Coords, Coords);
+
+/// number of symmetries of a square board, the rotations and reflections of the dihedral group
+pub const SYMMETRIES: usize = 8;
+
+/// maps a position of a `size` x `size` board by one of the [`SYMMETRIES`]: `sym % 4` quarter
+/// turns, followed by a transposition if `sym >= 4`
+pub fn symmetry(sym: usize, pos: Coords, size: usize) -> Coords {
+    let (mut i, mut j) = pos;
+    for _ in 0..sym % 4 {
+        (i, j) = (j, size - 1 - i);
+    }
+    if sym >= 4 {
+        (i, j) = (j, i);
+    }
+    return (i, j);
+}
+
+/// The status of a game, a typed version of the `winner` convention
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GameResult {
+    Ongoing,
+    Draw,
+    Player1,
+    Player2,
+}
+
+impl GameResult {
+    /// converts a winner as returned by [`GameState::winner`]
+    pub fn from_winner(winner: i64) -> Self {
+        return match winner {
+            0 => GameResult::Ongoing,
+            1 => GameResult::Player1,
+            2 => GameResult::Player2,
+            _ => GameResult::Draw,
+        }
+    }
+}
+
+/// The interface shared by the states of every game, so harness code can be written once
+pub trait GameState: Clone {
+    /// returns the legal moves of the current player
+    fn legal_moves(&self) -> Vec<Move>;
+
+    /// returns the number of legal moves, without building them
+    fn legal_move_count(&self) -> usize;
+
+    /// checks that a move is one of the legal moves, or returns the reason it is illegal
+    fn check_move(&self, c_move: Move) -> Result<(), &'static str>;
+
+    /// returns whether a move is one of the legal moves
+    fn is_legal(&self, c_move: Move) -> bool { return self.check_move(c_move).is_ok() }
+
+    /// plays a move and returns the following state. The move is not validated
+    fn play(&self, c_move: Move) -> Self;
+
+    /// returns 0 if the game is unfinished, -1 if it is a tie and the player id of the winner
+    /// otherwise
+    fn winner(&self) -> i64;
+
+    /// returns the status of the game, as given by `winner`
+    fn result(&self) -> GameResult { return GameResult::from_winner(self.winner()) }
+
+    /// returns the current score of both players
+    fn score(&self) -> (usize, usize);
+
+    /// returns the id of the player to move
+    fn curr_pid(&self) -> u32;
+
+    /// returns the number of moves played since the initial state
+    fn turn(&self) -> u32;
+}
+
+impl GameState for AvalamState {
+    fn legal_moves(&self) -> Vec<Move> { return AvalamState::legal_moves(self) }
+    fn legal_move_count(&self) -> usize { return AvalamState::legal_move_count(self) }
+    fn check_move(&self, c_move: Move) -> Result<(), &'static str> { return AvalamState::check_move(self, c_move) }
+    fn play(&self, c_move: Move) -> Self { return AvalamState::play(self, c_move) }
+    fn winner(&self) -> i64 { return AvalamState::winner(self) }
+    fn score(&self) -> (usize, usize) { return AvalamState::score(self) }
+    fn curr_pid(&self) -> u32 { return self.curr_pid }
+    fn turn(&self) -> u32 { return self.turn }
+}
+
+impl GameState for UltiTTTState {
+    fn legal_moves(&self) -> Vec<Move> { return UltiTTTState::legal_moves(self) }
+    fn legal_move_count(&self) -> usize { return UltiTTTState::legal_move_count(self) }
+    fn check_move(&self, c_move: Move) -> Result<(), &'static str> { return UltiTTTState::check_move(self, c_move) }
+    fn play(&self, c_move: Move) -> Self { return UltiTTTState::play(self, c_move) }
+    fn winner(&self) -> i64 { return UltiTTTState::winner(self) }
+    fn score(&self) -> (usize, usize) { return UltiTTTState::score(self) }
+    fn curr_pid(&self) -> u32 { return self.curr_pid }
+    fn turn(&self) -> u32 { return self.turn }
+}
+
+/// The games implemented in rust
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Game {
+    Avalam,
+    UltiTTT,
+}
+
+impl Game {
+    /// returns the initial state of the game
+    pub fn new_state(self) -> AnyState {
+        return match self {
+            Game::Avalam => AnyState::Avalam(AvalamState::new()),
+            Game::UltiTTT => AnyState::UltiTTT(UltiTTTState::new()),
+        }
+    }
+
+    /// returns the size of the fixed-width encoding of the states of the game
+    pub fn record_size(self) -> usize {
+        return match self {
+            Game::Avalam => Avalam::RECORD_SIZE,
+            Game::UltiTTT => UltiTTT::RECORD_SIZE,
+        }
+    }
+}
+
+/// A state of any of the games, dispatching the [`GameState`] methods to the right game
+// the variants are kept inline as states are cloned on every play
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AnyState {
+    Avalam(AvalamState),
+    UltiTTT(UltiTTTState),
+}
+
+impl AnyState {
+    /// returns the game this state belongs to
+    pub fn game(&self) -> Game {
+        return match self {
+            AnyState::Avalam(_) => Game::Avalam,
+            AnyState::UltiTTT(_) => Game::UltiTTT,
+        }
+    }
+
+    /// returns the fixed-width encoding of the state
+    pub fn to_bytes(&self) -> Vec<u8> {
+        return match self {
+            AnyState::Avalam(s) => s.to_bytes(),
+            AnyState::UltiTTT(s) => s.to_bytes(),
+        }
+    }
+
+    /// decodes a state of `game` from its fixed-width encoding, or returns the reason it is invalid
+    pub fn from_bytes(game: Game, bytes: &[u8]) -> Result<Self, String> {
+        return match game {
+            Game::Avalam => AvalamState::from_bytes(bytes).map(AnyState::Avalam),
+            Game::UltiTTT => UltiTTTState::from_bytes(bytes).map(AnyState::UltiTTT),
+        }
+    }
+}
+
+impl GameState for AnyState {
+    fn legal_moves(&self) -> Vec<Move> {
+        return match self {
+            AnyState::Avalam(s) => s.legal_moves(),
+            AnyState::UltiTTT(s) => s.legal_moves(),
+        }
+    }
+
+    fn legal_move_count(&self) -> usize {
+        return match self {
+            AnyState::Avalam(s) => s.legal_move_count(),
+            AnyState::UltiTTT(s) => s.legal_move_count(),
+        }
+    }
+
+    fn check_move(&self, c_move: Move) -> Result<(), &'static str> {
+        return match self {
+            AnyState::Avalam(s) => s.check_move(c_move),
+            AnyState::UltiTTT(s) => s.check_move(c_move),
+        }
+    }
+
+    fn play(&self, c_move: Move) -> Self {
+        return match self {
+            AnyState::Avalam(s) => AnyState::Avalam(s.play(c_move)),
+            AnyState::UltiTTT(s) => AnyState::UltiTTT(s.play(c_move)),
+        }
+    }
+
+    fn winner(&self) -> i64 {
+        return match self {
+            AnyState::Avalam(s) => s.winner(),
+            AnyState::UltiTTT(s) => s.winner(),
+        }
+    }
+
+    fn score(&self) -> (usize, usize) {
+        return match self {
+            AnyState::Avalam(s) => s.score(),
+            AnyState::UltiTTT(s) => s.score(),
+        }
+    }
+
+    fn curr_pid(&self) -> u32 {
+        return match self {
+            AnyState::Avalam(s) => s.curr_pid,
+            AnyState::UltiTTT(s) => s.curr_pid,
+        }
+    }
+
+    fn turn(&self) -> u32 {
+        return match self {
+            AnyState::Avalam(s) => s.turn,
+            AnyState::UltiTTT(s) => s.turn,
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! Move generation counts, to check the rules against known values and to benchmark them
+use crate::Core::GameState;
+
+/// returns the number of leaves of the game tree of `state` at `depth` plies. Finished games are
+/// leaves of the tree, so they only count at depth 0. The last ply is counted from
+/// [`GameState::legal_move_count`], without playing the moves
+pub fn perft<S: GameState>(state: &S, depth: u32) -> u64 {
+    if depth == 0 { return 1; }
+    if state.winner() != 0 { return 0; }
+    if depth == 1 { return state.legal_move_count() as u64; }
+
+    return state.legal_moves().into_iter()
+        .map(|c_move| perft(&state.play(c_move), depth - 1))
+        .sum();
+}
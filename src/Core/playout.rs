@@ -0,0 +1,69 @@
+//! Random playouts of the games, run without any python involvement
+use rayon::prelude::*;
+use crate::Core::{AnyState, Game, GameState};
+
+const GOLDEN_GAMMA: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// A small SplitMix64 generator, so playouts are reproducible from a seed
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        return Rng { state: seed };
+    }
+
+    /// returns the generator of one of the independent streams of a seed
+    pub fn for_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Rng::new(seed ^ stream.wrapping_mul(GOLDEN_GAMMA));
+        rng.next_u64();
+        return rng;
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        return z ^ (z >> 31);
+    }
+
+    /// returns a number in `0..n`
+    pub fn below(&mut self, n: usize) -> usize {
+        return (self.next_u64() % n as u64) as usize;
+    }
+}
+
+/// plays uniformly random moves from `state` until the game is over and returns the final state
+pub fn random_playout<S: GameState>(mut state: S, rng: &mut Rng) -> S {
+    while state.winner() == 0 {
+        let moves = state.legal_moves();
+        if moves.is_empty() { break; }
+        state = state.play(moves[rng.below(moves.len())]);
+    }
+    return state;
+}
+
+/// plays a random playout from each state on the rayon thread pool and returns the winner and
+/// the final score of every game, in the order of `states`. Each game uses its own stream of
+/// `seed`, so the results do not depend on the scheduling of the threads
+pub fn rollouts(states: &[AnyState], seed: u64) -> Vec<(i64, (usize, usize))> {
+    return states.par_iter().enumerate().map(|(i, state)| {
+        let mut rng = Rng::for_stream(seed, i as u64);
+        let end = random_playout(state.clone(), &mut rng);
+        (end.winner(), end.score())
+    }).collect();
+}
+
+/// plays `n` random games of `game` from its initial state on the rayon thread pool and returns
+/// the winner and the number of moves of every game. Game `i` uses the stream `i` of `seed`, so
+/// the results are reproducible
+pub fn run_games(game: Game, n: usize, seed: u64) -> Vec<(i64, u32)> {
+    return (0..n).into_par_iter().map(|i| {
+        let mut rng = Rng::for_stream(seed, i as u64);
+        let end = random_playout(game.new_state(), &mut rng);
+        (end.winner(), end.turn())
+    }).collect();
+}
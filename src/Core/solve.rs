@@ -0,0 +1,89 @@
+//! Exact solving of game endings with a depth limited negamax
+use crate::Core::GameState;
+
+/// default number of legal moves above which the python bindings refuse to solve a state
+pub const MAX_ROOT_MOVES: usize = 16;
+/// default number of states searched before [`solve`] gives up and returns None
+pub const MAX_NODES: u64 = 1 << 22;
+
+/// returns the winner of the game under optimal play of both players, with the convention of
+/// [`GameState::winner`] (-1 for a tie), if it is decided within `max_plies` moves. It returns
+/// None if the result depends on a line longer than `max_plies`, or if deciding it takes more
+/// than `max_nodes` states.
+///
+/// Every line is searched, so the cost grows as the number of legal moves to the power of
+/// `max_plies`. The node budget bounds it, so a call on a position with many moves returns None
+/// instead of running for hours
+pub fn solve<S: GameState>(state: &S, max_plies: u32, max_nodes: u64) -> Option<i64> {
+    let mut budget = max_nodes;
+    let pid = i64::from(state.curr_pid());
+    return match negamax(state, max_plies, &mut budget)? {
+        1 => Some(pid),
+        0 => Some(-1),
+        _ => Some((pid % 2) + 1),
+    }
+}
+
+/// returns the value of a state for its player to move: 1 for a win, 0 for a tie and -1 for a
+/// loss, or None if it cannot be decided within `depth` moves or the `budget` of states. Once
+/// the budget is spent every call returns None, which only discards the unfinished lines: a win
+/// found before is still exact
+fn negamax<S: GameState>(state: &S, depth: u32, budget: &mut u64) -> Option<i8> {
+    if *budget == 0 { return None; }
+    *budget -= 1;
+
+    let winner = state.winner();
+    if winner != 0 {
+        if winner == -1 { return Some(0); }
+        return Some(if winner == i64::from(state.curr_pid()) { 1 } else { -1 });
+    }
+    if depth == 0 { return None; }
+
+    let mut best: Option<i8> = None;
+    let mut unknown = false;
+    for c_move in state.legal_moves() {
+        match negamax(&state.play(c_move), depth - 1, budget) {
+            // no result can be better than a win
+            Some(-1) => return Some(1),
+            Some(v) => best = Some(best.map_or(-v, |b| b.max(-v))),
+            None => unknown = true,
+        }
+    }
+
+    // an undecided line could still be a win
+    if unknown { return None; }
+    return best;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{solve, MAX_NODES};
+    use crate::Core::Avalam::{AvalamState, SIZE};
+
+    /// three lone towers in a row, of player 2, player 1 and player 2, with player 2 to move
+    fn line() -> AvalamState {
+        let mut board = [[0; SIZE]; SIZE];
+        board[0][..3].copy_from_slice(&[-1, 1, -1]);
+        return AvalamState::from_board(&board, None).unwrap();
+    }
+
+    #[test]
+    fn solves_a_two_plies_ending() {
+        // player 2 stacks one of their towers on the one of player 1, which leaves player 1
+        // without any tower for the last merge. Moving the tower of player 1 isolates the third
+        // one for a tie, so the win takes two plies
+        let state = line();
+        assert_eq!(state.curr_pid, 2);
+        assert_eq!(solve(&state, 1, MAX_NODES), None);
+        assert_eq!(solve(&state, 2, MAX_NODES), Some(2));
+    }
+
+    #[test]
+    fn the_node_budget_gives_up() {
+        // the win is found after 4 states: the root, its first child and the two children of it
+        assert_eq!(solve(&line(), 2, 3), None);
+        assert_eq!(solve(&line(), 2, 4), Some(2));
+        assert_eq!(solve(&line(), 2, 0), None);
+        assert_eq!(solve(&AvalamState::new(), 6, 10_000), None);
+    }
+}
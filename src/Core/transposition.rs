@@ -0,0 +1,101 @@
+//! A transposition table storing search results by position hash
+use std::collections::HashMap;
+use crate::Core::Move;
+
+/// A search result stored in a [`TranspositionTable`]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Entry {
+    /// depth of the search that produced the entry
+    pub depth: u32,
+    pub value: f64,
+    pub best_move: Option<Move>,
+}
+
+enum Storage {
+    Unbounded(HashMap<u64, Entry>),
+    /// one slot per `hash % capacity`, with the number of filled slots
+    Fixed(Vec<Option<(u64, Entry)>>, usize),
+}
+
+/// Search results keyed by position hash. It either grows without limit, or keeps a fixed
+/// number of slots where an entry replaces the one in its slot only if it comes from a search at
+/// least as deep (depth-preferred replacement)
+pub struct TranspositionTable {
+    storage: Storage,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self { return Self::new() }
+}
+
+impl TranspositionTable {
+    /// returns an empty table without size limit
+    pub fn new() -> Self {
+        return TranspositionTable { storage: Storage::Unbounded(HashMap::new()) };
+    }
+
+    /// returns an empty table holding at most `capacity` entries. It panics if `capacity` is 0
+    pub fn with_capacity(capacity: usize) -> Self {
+        assert!(capacity > 0, "the capacity of a table must be positive");
+        return TranspositionTable { storage: Storage::Fixed(vec![None; capacity], 0) };
+    }
+
+    /// returns the entry stored for `hash`
+    pub fn get(&self, hash: u64) -> Option<&Entry> {
+        return match &self.storage {
+            Storage::Unbounded(map) => map.get(&hash),
+            Storage::Fixed(slots, _) => match &slots[Self::slot(slots, hash)] {
+                Some((h, entry)) if *h == hash => Some(entry),
+                _ => None,
+            },
+        }
+    }
+
+    /// stores an entry for `hash` and returns whether it was kept. In a fixed size table, it is
+    /// dropped if its slot holds an entry of a deeper search
+    pub fn put(&mut self, hash: u64, entry: Entry) -> bool {
+        return match &mut self.storage {
+            Storage::Unbounded(map) => {
+                map.insert(hash, entry);
+                true
+            },
+            Storage::Fixed(slots, len) => {
+                let i = Self::slot(slots, hash);
+                let slot = &mut slots[i];
+                match slot {
+                    Some((_, old)) if old.depth > entry.depth => false,
+                    _ => {
+                        if slot.is_none() { *len += 1; }
+                        *slot = Some((hash, entry));
+                        true
+                    },
+                }
+            },
+        }
+    }
+
+    /// returns the number of stored entries
+    pub fn len(&self) -> usize {
+        return match &self.storage {
+            Storage::Unbounded(map) => map.len(),
+            Storage::Fixed(_, len) => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool { return self.len() == 0 }
+
+    /// removes every entry
+    pub fn clear(&mut self) {
+        match &mut self.storage {
+            Storage::Unbounded(map) => map.clear(),
+            Storage::Fixed(slots, len) => {
+                slots.fill(None);
+                *len = 0;
+            },
+        }
+    }
+
+    fn slot(slots: &[Option<(u64, Entry)>], hash: u64) -> usize {
+        return (hash % slots.len() as u64) as usize;
+    }
+}
@@ -0,0 +1,454 @@
+use std::fmt;
+use ndarray::{Array2, Dimension};
+use numpy::{Element, PyArray};
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::{create_exception, pyclass, pyfunction, pymethods, Python, PyTypeInfo};
+use pyo3::exceptions::{PyKeyError, PyTypeError, PyUserWarning, PyValueError};
+use pyo3::types::{PyDict, PySet, PyString, PyType};
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, SeqAccess, Visitor};
+use crate::Core::{AnyState, Game, GameResult, GameState, Move};
+use crate::Core::{playout, solve};
+use crate::Core::Avalam::SIZE as AVALAM_SIZE;
+use crate::Core::UltiTTT::UltiTTTState;
+use crate::Avalam::RawAvalamState;
+use crate::Checkers::RawCheckersState;
+use crate::UltiTTT::RawUltiTTTState;
+
+
+create_exception!(
+    GameEngines, IllegalMoveError, PyValueError,
+    "Raised when playing an illegal move. The `move` and `reason` attributes give the move and why it is illegal"
+);
+
+/// returns an `IllegalMoveError` for a move, carrying the move and the reason it is illegal
+pub(crate) fn illegal_move(py: Python<'_>, c_move: Move, reason: &str) -> PyErr {
+    let err = IllegalMoveError::new_err(format!("illegal move {c_move:?}: {reason}"));
+    let value = err.value(py);
+    if let Err(e) = value.setattr("move", c_move).and_then(|_| value.setattr("reason", reason)) {
+        return e;
+    }
+    return err;
+}
+
+create_exception!(
+    GameEngines, StateLoadError, PyValueError,
+    "Raised when a state cannot be loaded from a file. The error of the save module, if any, is its cause"
+);
+
+/// loads a state of type `state_type` from `file` with the `load_state` method of a save module.
+/// It raises a `StateLoadError` naming the file if the save module fails or if it does not return
+/// a `state_type`, so a directory of saves can be loaded while skipping the corrupt ones
+pub(crate) fn load_state<'py>(
+    save_mod: &Bound<'py, PyType>, file: Bound<'py, PyString>, state_type: Bound<'py, PyType>,
+    kwargs: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyAny>> {
+    let py = file.py();
+    let name = file.to_string();
+    let state = save_mod.call_method("load_state", (file, &state_type), kwargs).map_err(|e| {
+        let err = StateLoadError::new_err(format!("could not load a state from {name}: {e}"));
+        err.set_cause(py, Some(e));
+        return err;
+    })?;
+
+    if !state.is_instance(&state_type)? {
+        return Err(StateLoadError::new_err(format!(
+            "could not load a state from {name}: the save module returned a {} instead of a {}",
+            state.get_type().name()?, state_type.name()?
+        )));
+    }
+    return Ok(state);
+}
+
+/// the version leading a MessagePack payload, read without the fields after it: they are skipped
+/// whatever their number and types, which change between versions
+struct PayloadVersion(u32);
+
+impl<'de> Deserialize<'de> for PayloadVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct VersionVisitor;
+
+        impl<'de> Visitor<'de> for VersionVisitor {
+            type Value = PayloadVersion;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                return f.write_str("a payload starting with its version");
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PayloadVersion, A::Error> {
+                let version = seq.next_element()?.ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                return Ok(PayloadVersion(version));
+            }
+        }
+
+        return deserializer.deserialize_seq(VersionVisitor);
+    }
+}
+
+/// checks the version of a MessagePack payload before decoding its fields, so that a payload of
+/// another version raises a `StateLoadError` about its version rather than a decoding error
+pub(crate) fn check_payload_version(data: &[u8], expected: u32) -> PyResult<()> {
+    let PayloadVersion(version) = rmp_serde::from_slice(data)
+        .map_err(|e| PyValueError::new_err(format!("malformed payload, {e}")))?;
+    if version != expected {
+        return Err(StateLoadError::new_err(format!("unsupported payload version {version}, expected {expected}")));
+    }
+    return Ok(());
+}
+
+/// whether the methods of each save module take the schema `version`, by module then by method
+/// name. Reading a signature is slow, so it is only done on the first call of each method; the
+/// modules are held weakly, so that the classes made on the fly, as in the tests, can be collected
+static VERSION_SUPPORT: GILOnceCell<Py<PyAny>> = GILOnceCell::new();
+
+/// keyword arguments giving the schema `version` to the `method` of a save module, or None if the
+/// method does not take a `version` argument. Save modules written before the versioning keep the
+/// `(file, state)` and `(file, state_type)` signatures and are called without it, with a
+/// UserWarning on the first call, as the version of their files is then never checked
+pub(crate) fn version_kwargs<'py>(save_mod: &Bound<'py, PyAny>, method: &str, version: u32) -> PyResult<Option<Bound<'py, PyDict>>> {
+    let py = save_mod.py();
+    let cache = VERSION_SUPPORT.get_or_try_init(py, || -> PyResult<_> {
+        return Ok(py.import("weakref")?.call_method0("WeakKeyDictionary")?.unbind());
+    })?.bind(py);
+
+    // a save module that cannot be weakly referenced is resolved again on every call
+    let methods = match cache.call_method1("setdefault", (save_mod, PyDict::new(py))) {
+        Ok(methods) => methods.downcast_into::<PyDict>()?,
+        Err(_) => PyDict::new(py),
+    };
+    let accepts = match methods.get_item(method)? {
+        Some(accepts) => accepts.extract::<bool>()?,
+        None => {
+            let accepts = accepts_version(&save_mod.getattr(method)?)?;
+            if !accepts {
+                let consequence = match method {
+                    "load_state" => "the version of the files it loads is not checked",
+                    _ => "the files it writes do not record their version",
+                };
+                let message = format!("{method} of the save module {} does not take a `version` argument, so {consequence}", save_mod.repr()?);
+                py.import("warnings")?.call_method1("warn", (message, py.get_type::<PyUserWarning>()))?;
+            }
+            methods.set_item(method, accepts)?;
+            accepts
+        }
+    };
+    if !accepts { return Ok(None); }
+
+    let kwargs = PyDict::new(py);
+    kwargs.set_item("version", version)?;
+    return Ok(Some(kwargs));
+}
+
+/// returns whether `method` takes a `version` keyword argument, by name or through `**kwargs`. A
+/// method whose signature cannot be read is assumed not to
+fn accepts_version(method: &Bound<'_, PyAny>) -> PyResult<bool> {
+    let inspect = method.py().import("inspect")?;
+    let Ok(signature) = inspect.call_method1("signature", (method,)) else { return Ok(false) };
+
+    let var_keyword = inspect.getattr("Parameter")?.getattr("VAR_KEYWORD")?;
+    let parameters = signature.getattr("parameters")?;
+    return Ok(parameters.contains("version")? || parameters.call_method0("values")?.try_iter()?
+        .any(|p| p.and_then(|p| p.getattr("kind")?.eq(&var_keyword)).unwrap_or(false)));
+}
+
+/// solves `state` with [`solve::solve`] without holding the GIL. It raises a ValueError if the
+/// state has more than `max_moves` legal moves, as the search could not end in a reasonable time
+pub(crate) fn solve_state<S: GameState + Sync>(
+    py: Python<'_>, state: &S, max_plies: u32, max_moves: usize, max_nodes: u64,
+) -> PyResult<Option<i64>> {
+    let moves = state.legal_move_count();
+    if moves > max_moves {
+        return Err(PyValueError::new_err(format!(
+            "the state has {moves} legal moves, more than the {max_moves} allowed for solving it"
+        )));
+    }
+    return Ok(py.allow_threads(|| solve::solve(state, max_plies, max_nodes)));
+}
+
+/// Counters of the `play` and `copy` calls made on the State objects, to profile searches from
+/// python. They use relaxed atomics, which are cheap enough not to skew the timings
+pub(crate) mod counters {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static PLAYS: AtomicU64 = AtomicU64::new(0);
+    static COPIES: AtomicU64 = AtomicU64::new(0);
+
+    pub(crate) fn count_play() { PLAYS.fetch_add(1, Ordering::Relaxed); }
+
+    pub(crate) fn count_copy() { COPIES.fetch_add(1, Ordering::Relaxed); }
+
+    pub(crate) fn get() -> (u64, u64) {
+        return (PLAYS.load(Ordering::Relaxed), COPIES.load(Ordering::Relaxed));
+    }
+
+    pub(crate) fn reset() {
+        PLAYS.store(0, Ordering::Relaxed);
+        COPIES.store(0, Ordering::Relaxed);
+    }
+}
+
+/// returns the `(plays, copies)` counts of the `play` and `copy` (or `lazy_copy`) calls made on
+/// State objects since the module was loaded or `reset_counters` was called
+#[pyfunction]
+pub fn get_counters() -> (u64, u64) {
+    return counters::get();
+}
+
+/// sets the counters of `get_counters` back to 0
+#[pyfunction]
+pub fn reset_counters() {
+    counters::reset();
+}
+
+/// The status of a game returned by the `result` method of the states, to check for the end of
+/// a game without interpreting the magic numbers of `winner`
+#[pyclass(name = "GameResult", eq, eq_int)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PyGameResult {
+    Ongoing,
+    Draw,
+    Player1,
+    Player2,
+}
+
+impl From<GameResult> for PyGameResult {
+    fn from(result: GameResult) -> Self {
+        return match result {
+            GameResult::Ongoing => PyGameResult::Ongoing,
+            GameResult::Draw => PyGameResult::Draw,
+            GameResult::Player1 => PyGameResult::Player1,
+            GameResult::Player2 => PyGameResult::Player2,
+        }
+    }
+}
+
+/// The games usable by a generic harness. Each method takes a state of the selected game and
+/// dispatches to its implementation, so a tournament runner can be written once for all games.
+///
+/// It is exposed as `GameKind` in python, since `GameEngines.Game` is the game runner class
+#[pyclass(name = "GameKind", eq, eq_int)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PyGame {
+    Avalam,
+    UltiTTT,
+}
+
+impl From<PyGame> for Game {
+    fn from(game: PyGame) -> Self {
+        return match game {
+            PyGame::Avalam => Game::Avalam,
+            PyGame::UltiTTT => Game::UltiTTT,
+        }
+    }
+}
+
+#[pymethods]
+impl PyGame {
+    /// creates the initial State object of the game
+    fn new_state<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        return match self {
+            PyGame::Avalam => RawAvalamState::type_object(py).call0(),
+            PyGame::UltiTTT => RawUltiTTTState::type_object(py).call0(),
+        }
+    }
+
+    /// returns the legal moves of a State object of the game
+    fn legal_moves<'py>(&self, state: &Bound<'py, PyAny>) -> PyResult<Bound<'py, PySet>> {
+        let py = state.py();
+        return match self {
+            PyGame::Avalam => PySet::new(py, state.downcast::<RawAvalamState>()?.borrow().state().legal_moves()),
+            PyGame::UltiTTT => PySet::new(py, state.downcast::<RawUltiTTTState>()?.borrow().state().legal_moves()),
+        }
+    }
+
+    /// plays a move on a State object of the game and returns the following State object. It
+    /// raises an `IllegalMoveError` if the move is not legal
+    fn play<'py>(&self, state: &Bound<'py, PyAny>, c_move: Move) -> PyResult<Bound<'py, PyAny>> {
+        let py = state.py();
+        return match self {
+            PyGame::Avalam => {
+                let s = state.downcast::<RawAvalamState>()?.borrow();
+                s.state().check_move(c_move).map_err(|reason| illegal_move(py, c_move, reason))?;
+                counters::count_play();
+                Ok(Bound::new(py, s.wrap(py, s.state().play(c_move)))?.into_any())
+            },
+            PyGame::UltiTTT => {
+                let s = state.downcast::<RawUltiTTTState>()?.borrow();
+                s.state().check_move(c_move).map_err(|reason| illegal_move(py, c_move, reason))?;
+                counters::count_play();
+                Ok(Bound::new(py, s.after(py, c_move))?.into_any())
+            },
+        }
+    }
+
+    /// returns the winner of a State object of the game, with the same convention as `winner`
+    fn winner(&self, state: &Bound<'_, PyAny>) -> PyResult<i64> {
+        return match self {
+            PyGame::Avalam => Ok(state.downcast::<RawAvalamState>()?.borrow().state().winner()),
+            PyGame::UltiTTT => Ok(state.downcast::<RawUltiTTTState>()?.borrow().state().winner()),
+        }
+    }
+}
+
+/// The legal moves of a state, returned by its `legal_moves` method. The moves are not built
+/// unless needed: the length and membership tests are computed from the rules directly, and
+/// iterating yields the moves one at a time
+#[pyclass]
+pub struct LegalMoves {
+    state: AnyState,
+}
+
+impl LegalMoves {
+    pub(crate) fn new(state: AnyState) -> Self { return LegalMoves { state } }
+}
+
+#[pymethods]
+impl LegalMoves {
+    fn __len__(&self) -> usize { return self.state.legal_move_count() }
+
+    fn __contains__(&self, c_move: &Bound<'_, PyAny>) -> bool {
+        return match c_move.extract::<Move>() {
+            Ok(c_move) => self.state.is_legal(c_move),
+            Err(_) => false,
+        }
+    }
+
+    /// returns a one-shot iterator over the moves: it is exhausted after a single pass, and a new
+    /// one is created on every `iter` call. Breaking early skips the remaining computation for
+    /// UltiTTT, as its moves are found during the iteration
+    fn __iter__(&self) -> LegalMovesIter {
+        let cursor = match &self.state {
+            AnyState::Avalam(s) => MovesCursor::Built(s.legal_moves().into_iter()),
+            AnyState::UltiTTT(s) => MovesCursor::Scan(s.clone(), 0),
+        };
+        return LegalMovesIter { cursor };
+    }
+}
+
+enum MovesCursor {
+    /// moves generated ahead of the iteration
+    Built(std::vec::IntoIter<Move>),
+    /// the state being scanned and the next cell to check, in the row order of `board`
+    Scan(UltiTTTState, usize),
+}
+
+/// One-shot iterator over the moves of a [`LegalMoves`]
+#[pyclass]
+pub struct LegalMovesIter {
+    cursor: MovesCursor,
+}
+
+#[pymethods]
+impl LegalMovesIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> { return slf }
+
+    fn __next__(&mut self) -> Option<Move> {
+        return match &mut self.cursor {
+            MovesCursor::Built(moves) => moves.next(),
+            MovesCursor::Scan(state, cell) => {
+                while *cell < 81 {
+                    let (i, j) = (*cell / 9, *cell % 9);
+                    *cell += 1;
+
+                    let c_move = ((i / 3, i % 3), (j / 3, j % 3));
+                    if state.is_legal(c_move) { return Some(c_move); }
+                }
+                None
+            },
+        }
+    }
+}
+
+/// returns the rust state wrapped by a State object of any game
+pub(crate) fn any_state(state: &Bound<'_, PyAny>) -> PyResult<AnyState> {
+    if let Ok(s) = state.downcast::<RawAvalamState>() {
+        return Ok(AnyState::Avalam(s.borrow().state().clone()));
+    }
+    if let Ok(s) = state.downcast::<RawUltiTTTState>() {
+        return Ok(AnyState::UltiTTT(s.borrow().state().clone()));
+    }
+    return Err(PyTypeError::new_err(format!("{} is not a state of a rust engine", state.get_type())));
+}
+
+/// marks a numpy array mirroring a state as read-only with numpy's `setflags` and returns it.
+/// The rules only read the wrapped state, so writing in place into its arrays would silently be
+/// ignored: it raises a ValueError instead, and the arrays have to be replaced through the setters
+pub(crate) fn read_only<'py, T: Element, D: Dimension>(array: Bound<'py, PyArray<T, D>>) -> PyResult<Bound<'py, PyArray<T, D>>> {
+    let kwargs = PyDict::new(array.py());
+    kwargs.set_item("write", false)?;
+    array.call_method("setflags", (), Some(&kwargs))?;
+    return Ok(array);
+}
+
+/// returns a cell holding the same array as `cell` if it was already built, or an empty cell
+pub(crate) fn shared<T>(py: Python<'_>, cell: &GILOnceCell<Py<T>>) -> GILOnceCell<Py<T>> {
+    let shared = GILOnceCell::new();
+    if let Some(array) = cell.get(py) {
+        let _ = shared.set(py, array.clone_ref(py));
+    }
+    return shared;
+}
+
+/// packs moves in a (N, 4) array, with one `[origin_row, origin_col, dest_row, dest_col]` row per
+/// move for Avalam and one `[sup_row, sup_col, sub_row, sub_col]` row per move for UltiTTT
+pub(crate) fn moves_array(moves: &[Move]) -> Array2<i64> {
+    return Array2::from_shape_fn((moves.len(), 4), |(m, k)| {
+        let ((a, b), (c, d)) = moves[m];
+        [a, b, c, d][k] as i64
+    });
+}
+
+/// extracts the value of `key` in a dict given to `from_dict`, raising a KeyError if it is missing
+pub(crate) fn dict_item<'py, T: FromPyObject<'py>>(data: &Bound<'py, PyDict>, key: &str) -> PyResult<T> {
+    return match data.get_item(key)? {
+        Some(v) => T::extract_bound(&v),
+        None => Err(PyKeyError::new_err(key.to_string())),
+    }
+}
+
+/// wraps a rust state in a State object of its game, using the default save module
+pub(crate) fn py_state(py: Python<'_>, state: AnyState) -> PyResult<Bound<'_, PyAny>> {
+    return match state {
+        AnyState::Avalam(s) => Ok(Bound::new(py, RawAvalamState::from_core(s))?.into_any()),
+        AnyState::UltiTTT(s) => Ok(Bound::new(py, RawUltiTTTState::from_core(s))?.into_any()),
+    }
+}
+
+/// plays a random game from each of the given states on a rust thread pool and returns the
+/// `(winner, score)` of every game, in the order of `states`.
+///
+/// The GIL is released during the simulation, so other python threads keep running. An asyncio
+/// event loop can await the results with `loop.run_in_executor(None, rollouts, states, seed)`
+#[pyfunction]
+#[pyo3(signature=(states, seed=0))]
+pub fn rollouts(py: Python<'_>, states: Vec<Bound<'_, PyAny>>, seed: u64) -> PyResult<Vec<(i64, (usize, usize))>> {
+    let states = states.iter().map(any_state).collect::<PyResult<Vec<AnyState>>>()?;
+    return Ok(py.allow_threads(|| playout::rollouts(&states, seed)));
+}
+
+/// plays `n` full random games of `game` on a rust thread pool and returns the `(winner, moves)`
+/// of every game, where `moves` is the number of moves played. Unlike `rollouts`, the games start
+/// from the initial state, to measure the throughput of whole games.
+///
+/// The GIL is released during the simulation, and the results only depend on `seed`
+#[pyfunction]
+#[pyo3(signature=(n, game, seed=0))]
+pub fn run_games(py: Python<'_>, n: usize, game: PyGame, seed: u64) -> Vec<(i64, u32)> {
+    return py.allow_threads(|| playout::run_games(game.into(), n, seed));
+}
+
+/// returns the build metadata of the engines: the crate `version` and the `games` available,
+/// mapped to the `(rows, cols)` dimensions of their board
+#[pyfunction]
+pub fn metadata(py: Python<'_>) -> PyResult<Bound<'_, PyDict>> {
+    let games = PyDict::new(py);
+    games.set_item("Avalam", (AVALAM_SIZE, AVALAM_SIZE))?;
+    games.set_item("Checkers", RawCheckersState::base_array().dim())?;
+    games.set_item("UltiTTT", UltiTTTState::DIMENSIONS)?;
+
+    let data = PyDict::new(py);
+    data.set_item("version", env!("CARGO_PKG_VERSION"))?;
+    data.set_item("games", games)?;
+    return Ok(data);
+}
@@ -0,0 +1,52 @@
+use pyo3::prelude::*;
+use pyo3::{pyclass, pymethods};
+use pyo3::exceptions::PyValueError;
+use crate::Core::Move;
+use crate::Core::transposition::{Entry, TranspositionTable};
+
+
+/// Python wrapper of a [`TranspositionTable`], storing the `(depth, value, best_move)` of
+/// searched positions by hash. With a `capacity`, the table has a fixed size and keeps the
+/// entries of the deepest searches when two positions share a slot
+#[pyclass(name = "TranspositionTable")]
+pub struct PyTranspositionTable {
+    table: TranspositionTable,
+}
+
+#[pymethods]
+impl PyTranspositionTable {
+    #[new]
+    #[pyo3(signature=(capacity=None))]
+    fn new(capacity: Option<usize>) -> PyResult<Self> {
+        let table = match capacity {
+            None => TranspositionTable::new(),
+            Some(0) => return Err(PyValueError::new_err("the capacity of a table must be positive")),
+            Some(capacity) => TranspositionTable::with_capacity(capacity),
+        };
+        return Ok(PyTranspositionTable { table });
+    }
+
+    /// returns the `(depth, value, best_move)` stored for `hash`, or None
+    fn get(&self, hash: &Bound<'_, PyAny>) -> PyResult<Option<(u32, f64, Option<Move>)>> {
+        return Ok(self.table.get(key(hash)?).map(|e| (e.depth, e.value, e.best_move)));
+    }
+
+    /// stores a search result for `hash` and returns whether it was kept, which is not the case
+    /// in a fixed size table when the slot holds the result of a deeper search
+    #[pyo3(signature=(hash, depth, value, best_move=None))]
+    fn put(&mut self, hash: &Bound<'_, PyAny>, depth: u32, value: f64, best_move: Option<Move>) -> PyResult<bool> {
+        return Ok(self.table.put(key(hash)?, Entry { depth, value, best_move }));
+    }
+
+    /// removes every entry
+    fn clear(&mut self) { self.table.clear() }
+
+    fn __len__(&self) -> usize { return self.table.len() }
+}
+
+/// returns the key of a python int in the table. The result of `hash()` can be negative, so the
+/// ints from -2^63 to 2^64 - 1 are accepted, the negative ones read as their two's complement
+fn key(hash: &Bound<'_, PyAny>) -> PyResult<u64> {
+    if let Ok(h) = hash.extract::<i64>() { return Ok(h as u64); }
+    return hash.extract::<u64>();
+}
@@ -1,31 +1,54 @@
-use std::collections::{HashSet};
-use itertools::{Itertools};
+use std::path::PathBuf;
 use ndarray::{Array2};
+use ndarray_npy::{read_npy, write_npy};
 use numpy::{PyArray2, PyArrayMethods};
 use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
 use pyo3::{Py, pyclass, pymethods, Python, PyTypeInfo};
-use pyo3::types::{PyBool, PyNotImplemented, PySet, PyString, PyType};
+use pyo3::exceptions::{PyIndexError, PyIOError, PyValueError};
+use pyo3::types::{PyBool, PyBytes, PyDict, PySet, PyString, PyType};
+use serde::{Deserialize, Serialize};
 use pyo3::basic::CompareOp;
-type Coords = (usize, usize);
-type Move = (Coords, Coords);
+use crate::Core::{AnyState, GameState, Move};
+use crate::Core::UltiTTT::{get_winner_of, sub_board_winners, UltiTTTState};
+use crate::Core::solve::{MAX_NODES, MAX_ROOT_MOVES};
+use crate::Game::{check_payload_version, counters, dict_item, illegal_move, load_state, moves_array, read_only, shared, solve_state, version_kwargs, LegalMoves, PyGameResult};
+
+/// version of the state representation written in the files of the save module
+const SCHEMA_VERSION: u32 = 1;
+
+/// version of the MessagePack payload, to be bumped whenever its fields change
+const MSGPACK_VERSION: u32 = 1;
+
+/// MessagePack payload of a state
+#[derive(Serialize, Deserialize)]
+struct UltiTTTPayload {
+    version: u32,
+    board: Vec<i64>,
+    win_state: [i64; 9],
+    active_cell: i64,
+    turn: u32,
+    curr_pid: u32,
+}
 
 
-#[derive(Clone)]
+/// Python wrapper of an [`UltiTTTState`]. The board of the state is mirrored in a numpy array,
+/// which is the external API of the class. The array is read-only, as the rules only read the
+/// wrapped state: it is replaced through the setter, which updates the state. The array is built
+/// on its first access, so the states a search plays and discards never allocate it.
+///
+/// `history` holds the moves played since the initial state, or None for a state built
+/// otherwise (loaded, decoded or changed through the setters). It is not part of the
+/// comparisons, so states reached in a different order are equal.
 #[pyclass(subclass, dict)]
 pub struct RawUltiTTTState {
-    #[pyo3(get, set)]
-    _board: Py<PyArray2<i64>>,
-    #[pyo3(get, set)]
-    _turn: u32,
-    #[pyo3(get, set)]
-    _curr_pid: u32,
-    #[pyo3(get, set)]
-    _win_state: [i64; 9],
-    #[pyo3(get, set)]
-    _active_cell: i64,
+    _board: GILOnceCell<Py<PyArray2<i64>>>,
 
     #[pyo3(get, set)]
-    _save_mod: Py<PyType>
+    _save_mod: Py<PyType>,
+
+    state: UltiTTTState,
+    history: Option<Vec<Move>>,
 }
 
 unsafe impl Send for RawUltiTTTState {}
@@ -37,6 +60,52 @@ impl RawUltiTTTState {
             SaveModule.getattr("UltiTTTSave").unwrap().extract().unwrap()
         })
     }
+
+    /// wraps a state in a python UltiTTT State object
+    fn from_state(state: UltiTTTState, save_mod: Py<PyType>) -> Self {
+        return RawUltiTTTState {
+            _board: GILOnceCell::new(),
+            _save_mod: save_mod,
+            state,
+            history: None,
+        };
+    }
+
+    /// returns the numpy board of the state, built on its first access
+    fn board_py(&self, py: Python) -> PyResult<&Py<PyArray2<i64>>> {
+        return self._board.get_or_try_init(py, || -> PyResult<_> {
+            return Ok(read_only(PyArray2::from_owned_array(py, Self::board_array(&self.state)))?.unbind());
+        });
+    }
+
+    fn board_array(state: &UltiTTTState) -> Array2<i64> {
+        return Array2::from_shape_fn(UltiTTTState::DIMENSIONS, |(i, j)| state.board[i][j]);
+    }
+
+    /// wraps a state in a python UltiTTT State object sharing this object's save module
+    pub(crate) fn wrap(&self, py: Python, state: UltiTTTState) -> Self {
+        return Self::from_state(state, self._save_mod.clone_ref(py));
+    }
+
+    /// plays a move, which is not validated, and wraps the following state with this object's
+    /// history followed by the move
+    pub(crate) fn after(&self, py: Python, c_move: Move) -> Self {
+        let mut next = self.wrap(py, self.state.play(c_move));
+        next.history = self.history.as_ref().map(|history| {
+            let mut moves = history.clone();
+            moves.push(c_move);
+            return moves;
+        });
+        return next;
+    }
+
+    /// wraps a state in a python UltiTTT State object using the default save module
+    pub(crate) fn from_core(state: UltiTTTState) -> Self {
+        return Self::from_state(state, Self::default_save_mod());
+    }
+
+    /// the wrapped state
+    pub(crate) fn state(&self) -> &UltiTTTState { return &self.state }
 }
 
 #[pymethods]
@@ -44,93 +113,181 @@ impl RawUltiTTTState {
     #[new]
     #[pyo3(signature=(save_module=None))]
     /// Creates the initial UltiTTT State python object
-    fn new<'py>(py: Python<'py>, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+    fn new<'py>(save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
         let ultittt_save: Py<PyType> = match save_module {
             None => { Self::default_save_mod()}
             Some(save_mod) => {save_mod.unbind()}
         };
 
-        let board = PyArray2::from_owned_array(py, Array2::zeros((9, 9)));
+        let mut state = Self::from_state(UltiTTTState::new(), ultittt_save);
+        state.history = Some(Vec::new());
+        return Ok(state);
+    }
+
+    /// rebuilds a state by playing `moves` from the initial state, such as the `moves_played` of
+    /// another state. It raises an `IllegalMoveError` if one of the moves is not legal
+    #[staticmethod]
+    #[pyo3(signature=(moves, save_module=None))]
+    fn from_moves<'py>(py: Python<'py>, moves: Vec<Move>, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let mut state = Self::new(save_module)?;
+        for c_move in moves {
+            state = state.play(py, c_move)?;
+        }
+        return Ok(state);
+    }
 
-        return Ok(RawUltiTTTState {
-            _board: board.unbind(),
-            _turn: 0,
-            _win_state: [0; 9],
-            _active_cell: -1,
-            _curr_pid: 1,
-            _save_mod: ultittt_save
+    /// returns the moves played since the initial state, in order. It raises a ValueError if the
+    /// state was not reached by playing from the initial state, as when it is loaded from a file
+    fn moves_played(&self) -> PyResult<Vec<Move>> {
+        return self.history.clone().ok_or_else(|| {
+            PyValueError::new_err("the moves leading to this state are unknown, as it was not played from the initial state")
         });
     }
 
     /// copies and returns a python UltiTTT State object
     fn copy<'py>(&self, py: Python<'py>) -> PyResult<Self> {
-        let board = unsafe { PyArray2::new(py, self._board.bind(py).dims(), false) };
-        self._board.bind(py).copy_to(&board).expect("");
-
-        return Ok(RawUltiTTTState{
-            _board: board.unbind(),
-            _turn: self._turn,
-            _win_state: self._win_state,
-            _active_cell: self._active_cell,
-            _curr_pid: self._curr_pid,
-            _save_mod: self._save_mod.clone()
-        });
+        counters::count_copy();
+        let mut copy = self.wrap(py, self.state.clone());
+        copy.history = self.history.clone();
+        return Ok(copy);
     }
 
-    /// play an action on the UltiTTT State and returns the following State object
-    fn play<'py>(&self, py: Python<'py>, c_move: Move) -> PyResult<Self> {
-        let sup_cell = c_move.0;
-        let sub_cell = c_move.1;
-
-        let sup_i = 3 * sup_cell.0 + sup_cell.1;
-        let sub_i = 3 * sub_cell.0 + sub_cell.1;
-
-        let mut new_board = self.copy(py)?;
-
-        let b_ref = new_board._board.bind(py);
-        b_ref.set_item((sup_i, sub_i), self._curr_pid).expect("Cell outside expected range");
+    /// implements `copy.copy` as `copy`: the copy has its own numpy array, while the save module
+    /// is shared as it is a type rather than state of the object
+    fn __copy__<'py>(&self, py: Python<'py>) -> PyResult<Self> {
+        return self.copy(py);
+    }
 
-        new_board._win_state[sup_i] = get_winner_of(
-            unsafe { b_ref.as_array() }.row(sup_i).iter()
-        );
+    /// implements `copy.deepcopy` as `copy`, which already gives a fresh numpy array. The
+    /// wrapped state holds no python objects, so `memo` is not needed
+    fn __deepcopy__<'py>(&self, py: Python<'py>, _memo: &Bound<'py, PyAny>) -> PyResult<Self> {
+        return self.copy(py);
+    }
 
-        new_board._turn += 1;
-        new_board._active_cell =
-            if new_board._win_state[sub_i] != 0 { -1 }
-            else { i64::try_from(sub_i).expect("Cell outside expected range") };
+    /// copies the State object without copying its numpy array: if this object already built it,
+    /// it is shared with the copy, and it is otherwise built separately on its first access. The
+    /// shared array must not be modified in place
+    fn lazy_copy<'py>(&self, py: Python<'py>) -> Self {
+        counters::count_copy();
+        return RawUltiTTTState {
+            _board: shared(py, &self._board),
+            _save_mod: self._save_mod.clone_ref(py),
+            state: self.state.clone(),
+            history: self.history.clone(),
+        };
+    }
 
-        new_board._curr_pid = (self._curr_pid % 2) + 1;
-        return Ok(new_board)
+    /// play an action on the UltiTTT State and returns the following State object. It raises an
+    /// `IllegalMoveError` if the move is not legal
+    fn play<'py>(&self, py: Python<'py>, c_move: Move) -> PyResult<Self> {
+        self.state.check_move(c_move).map_err(|reason| illegal_move(py, c_move, reason))?;
+        counters::count_play();
+        return Ok(self.after(py, c_move));
     }
 
     /// standard implementation of the `get_legal_moves` python method. it returns the legal
     /// actions the specified player can take.
     fn get_legal_moves<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PySet>> {
-        let board = unsafe { self._board.bind(py).as_array() };
-        let active_cell = usize::try_from(self._active_cell);
+        return PySet::new(py, self.state.legal_moves());
+    }
 
-        let condition: Box<dyn Fn(i64, usize) -> bool> = if self._active_cell == -1 || self._win_state[active_cell?] != 0 {
-            Box::new(|v: i64, i: usize| -> bool { v == 0 && self._win_state[i] == 0 })
-        } else {
-            Box::new(|v: i64, i: usize| -> bool { v == 0  && i == active_cell.unwrap() })
-        };
+    /// returns the legal moves as a (N, 4) numpy array, with one `[sup_row, sup_col, sub_row,
+    /// sub_col]` row per move. It can be used directly as the index of a policy mask
+    fn legal_moves_array<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<i64>> {
+        return PyArray2::from_owned_array(py, moves_array(&self.state.legal_moves()));
+    }
 
-        let moves = board.indexed_iter().filter_map(|((i,j),&v)| {
-            if condition(v, i) { Some(((i / 3, i % 3), (j / 3, j % 3))) } else { None }
-        }).collect::<HashSet<Move>>();
+    /// returns whether a move is legal: its cell is empty, its sub-board is undecided and, unless
+    /// the move is free, it is played in the forced sub-board. Only this move is checked, so it is
+    /// a cheap way to validate a move given by a user before playing it
+    fn is_legal(&self, c_move: Move) -> bool {
+        return self.state.is_legal(c_move);
+    }
 
-        return PySet::new(py, &moves)
+    /// returns the legal moves of the state as a `LegalMoves` object, which gives their number
+    /// and membership tests without building the whole set
+    fn legal_moves(&self) -> LegalMoves {
+        return LegalMoves::new(AnyState::UltiTTT(self.state.clone()));
     }
 
     /// returns the current score of the State. In the case of UltiTTT, this means the number of
     /// won sub-boards
     fn score(&self) -> (usize, usize) {
-        let w = self.winner();
-        return match w {
-            1 => (1, 0),
-            2 => (0, 1),
-            _ => (0, 0),
-        };
+        return self.state.score();
+    }
+
+    /// returns the id (1 or 2) of the player to move, which is the value its pieces take on the
+    /// board
+    fn current_player(&self) -> i64 {
+        return i64::from(self.state.curr_pid);
+    }
+
+    /// returns the number of cells of player 1, of player 2 and of empty cells in the sub-board
+    /// `idx`, numbered from 0 to 8 in row order
+    fn sub_board_counts(&self, idx: usize) -> PyResult<(usize, usize, usize)> {
+        if idx >= 9 {
+            return Err(PyIndexError::new_err(format!("sub-board index {idx} outside of 0..9")));
+        }
+        return Ok(self.state.sub_board_counts(idx));
+    }
+
+    /// returns the number of empty cells of each sub-board, in row order, or 0 for the decided
+    /// ones. Unlike `legal_move_count`, which is limited to the forced sub-board, it gives the
+    /// playable cells of every region, to find the contested and nearly full sub-boards
+    fn sub_board_mobility(&self) -> [usize; 9] {
+        return self.state.sub_board_mobility();
+    }
+
+    /// returns whether the game is a draw: every sub-board is decided and no player won three of
+    /// them in a line. `winner` also returns -1 for a full meta-board that has such a line, so
+    /// this is the unambiguous draw check
+    fn is_draw(&self) -> bool {
+        return self.state.is_draw();
+    }
+
+    /// returns whether `player` won three sub-boards in a line of the meta-board. It raises a
+    /// ValueError if `player` is not 1 or 2
+    fn win_for(&self, player: i64) -> PyResult<bool> {
+        if player != 1 && player != 2 {
+            return Err(PyValueError::new_err(format!("invalid player {player}, expected 1 or 2")));
+        }
+        return Ok(self.state.win_for(player));
+    }
+
+    /// returns the winner of a sub-board given as its 9 cells in row order, with the convention of
+    /// `winner`. It is used by the save module to rebuild the won sub-boards of older saves
+    #[staticmethod]
+    fn _get_winner_of(cells: [i64; 9]) -> i64 {
+        return get_winner_of(&cells);
+    }
+
+    /// returns the winner of each sub-board of a (9, 9) board, computed in a single pass. It
+    /// gives the same result as `_get_winner_of` called on every sub-board
+    #[staticmethod]
+    fn _sub_board_winners(board: [[i64; 9]; 9]) -> [i64; 9] {
+        return sub_board_winners(&board);
+    }
+
+    /// returns whether the two states are equal under one of the 8 rotations and reflections of
+    /// the board. The active cell is mapped with its sub-board, so positions that only differ by
+    /// their orientation have the same outcome and a search can treat them as a single node
+    fn is_symmetric_to(&self, other: &Self) -> bool {
+        return self.state.is_symmetric_to(&other.state);
+    }
+
+    /// returns the winner of the game under optimal play of both players (-1 for a tie) if it is
+    /// decided within `max_plies` moves, and None otherwise. The tree is searched without holding
+    /// the GIL, which is feasible late in the game when the forced sub-boards leave few moves. It
+    /// raises a ValueError if the state has more than `max_moves` legal moves, and it returns
+    /// None if the result is not found within `max_nodes` states
+    #[pyo3(signature=(max_plies, max_moves=MAX_ROOT_MOVES, max_nodes=MAX_NODES))]
+    fn solve(&self, py: Python<'_>, max_plies: u32, max_moves: usize, max_nodes: u64) -> PyResult<Option<i64>> {
+        return solve_state(py, &self.state, max_plies, max_moves, max_nodes);
+    }
+
+    /// returns the score of player 1 minus the score of player 2, the key used to order states
+    fn score_difference(&self) -> i64 {
+        return self.state.score_difference();
     }
 
     /// return the current winner of the game.
@@ -141,14 +298,29 @@ impl RawUltiTTTState {
     ///
     /// Otherwise, it returns the player id of the winner
     fn winner(&self) -> i64{
-        return get_winner_of(self._win_state.iter())
+        return self.state.winner();
     }
 
-    fn save<'py>(slf: Bound<'py, Self>, file: Bound<'py, PyAny>) {
-        let save_mod = slf.getattr("_save_mod").unwrap();
-        save_mod.call_method("save_state", (file, slf), None).unwrap();
+    /// returns the status of the game as a `GameResult`, the typed version of `winner`
+    fn result(&self) -> PyGameResult {
+        return GameState::result(&self.state).into();
     }
 
+    /// version of the state representation, given to the save module so files written for
+    /// another version are rejected when loaded
+    #[classattr]
+    const SCHEMA_VERSION: u32 = SCHEMA_VERSION;
+
+    /// saves the state in a file with its save module, tagged with the `SCHEMA_VERSION`
+    fn save<'py>(slf: Bound<'py, Self>, file: Bound<'py, PyAny>) -> PyResult<()> {
+        let save_mod = slf.getattr("_save_mod")?;
+        let kwargs = version_kwargs(&save_mod, "save_state", SCHEMA_VERSION)?;
+        save_mod.call_method("save_state", (file, slf), kwargs.as_ref())?;
+        return Ok(());
+    }
+
+    /// loads a state from a file with the save module, checking its `SCHEMA_VERSION`. It raises a
+    /// `StateLoadError` if the file cannot be loaded
     #[staticmethod]
     #[pyo3(signature=(file, save_module=None))]
     fn load<'py>(file: Bound<'py, PyString>, save_module: Option<Bound<'py, PyType>>) -> PyResult<Bound<'py, PyAny>> {
@@ -158,72 +330,251 @@ impl RawUltiTTTState {
         };
 
         let py = file.py();
-        avalam_save.bind(py.clone()).call_method(
-            "load_state",  (file, Self::type_object(py.clone())), None
-        )
+        let save_mod = avalam_save.bind(py);
+        let kwargs = version_kwargs(save_mod.as_any(), "load_state", SCHEMA_VERSION)?;
+        return load_state(save_mod, file, Self::type_object(py), kwargs.as_ref());
+    }
+
+    /// writes the board of the state in numpy's `.npy` format
+    fn save_board_npy(&self, path: PathBuf) -> PyResult<()> {
+        write_npy(&path, &Self::board_array(&self.state)).map_err(npy_error)?;
+        return Ok(());
+    }
+
+    /// reads a board written in numpy's `.npy` format. As the file only holds the board, the
+    /// won sub-boards are recomputed from it, the turn is the number of filled cells, the current
+    /// player is deduced from the turn and the next move is free. It raises a ValueError if a cell
+    /// is not 0, 1 or 2 or if the piece counts cannot happen in a game
+    #[staticmethod]
+    #[pyo3(signature=(path, save_module=None))]
+    fn load_board_npy<'py>(path: PathBuf, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let board: Array2<i64> = read_npy(&path).map_err(npy_error)?;
+
+        if board.dim() != UltiTTTState::DIMENSIONS {
+            return Err(PyValueError::new_err(format!("expected a {:?} board, got {:?}", UltiTTTState::DIMENSIONS, board.dim())));
+        }
+
+        let board: [[i64; 9]; 9] = std::array::from_fn(|i| std::array::from_fn(|j| board[(i, j)]));
+        let state = UltiTTTState::from_board(&board).map_err(PyValueError::new_err)?;
+
+        let ultittt_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, ultittt_save));
+    }
+
+    /// returns a deterministic byte encoding of the position, meant to be hashed by user-side
+    /// transposition tables
+    fn fingerprint<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        return PyBytes::new(py, &self.state.fingerprint());
+    }
+
+    /// returns the fixed-width byte encoding of the whole state, read back with `from_bytes`
+    fn to_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        return PyBytes::new(py, &self.state.to_bytes());
+    }
+
+    /// creates a state from the fixed-width encoding given by `to_bytes`
+    #[staticmethod]
+    #[pyo3(signature=(data, save_module=None))]
+    fn from_bytes<'py>(data: &[u8], save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let state = UltiTTTState::from_bytes(data).map_err(PyValueError::new_err)?;
+
+        let save_mod: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, save_mod));
+    }
+
+    /// returns a human readable encoding of the state, read back with `from_fen`, to share
+    /// positions in bug reports and test cases. The grammar is given by [`UltiTTTState::to_fen`]: for
+    /// example, the initial state is `9/9/9/9/9/9/9/9/9 - 1 0`
+    fn fen(&self) -> String {
+        return self.state.to_fen();
+    }
+
+    /// creates a state from the encoding given by `fen`. It raises a ValueError describing the
+    /// error if the encoding is invalid
+    #[staticmethod]
+    #[pyo3(signature=(fen, save_module=None))]
+    fn from_fen<'py>(fen: &str, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let state = UltiTTTState::from_fen(fen).map_err(|reason| PyValueError::new_err(format!("invalid fen {fen:?}: {reason}")))?;
+
+        let ultittt_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, ultittt_save));
+    }
+
+    /// returns the state as a dict of plain python lists and ints, read back with `from_dict`.
+    /// The board is a (9, 9) nested list with a row per sub-board
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let data = PyDict::new(py);
+        data.set_item("board", self.state.board)?;
+        data.set_item("win_state", self.state.win_state())?;
+        data.set_item("active_cell", self.state.active_cell)?;
+        data.set_item("turn", self.state.turn)?;
+        data.set_item("curr_pid", self.state.curr_pid)?;
+        return Ok(data);
+    }
+
+    /// creates a state from the dict given by `to_dict`. It raises a ValueError if a cell, a won
+    /// sub-board, the active cell or the player to move is out of range
+    #[staticmethod]
+    #[pyo3(signature=(data, save_module=None))]
+    fn from_dict<'py>(data: &Bound<'py, PyDict>, save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        let mut state = UltiTTTState::new();
+        state.board = dict_item(data, "board")?;
+        state.set_win_state(dict_item(data, "win_state")?);
+        state.active_cell = dict_item(data, "active_cell")?;
+        state.turn = dict_item(data, "turn")?;
+        state.curr_pid = dict_item(data, "curr_pid")?;
+        state.check_invariants().map_err(PyValueError::new_err)?;
+
+        let ultittt_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, ultittt_save));
+    }
+
+    /// returns a compact MessagePack encoding of the state, read back with `from_msgpack`
+    fn to_msgpack<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let payload = UltiTTTPayload {
+            version: MSGPACK_VERSION,
+            board: self.state.board.iter().flatten().copied().collect(),
+            win_state: self.state.win_state(),
+            active_cell: self.state.active_cell,
+            turn: self.state.turn,
+            curr_pid: self.state.curr_pid,
+        };
+        let data = rmp_serde::to_vec(&payload).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        return Ok(PyBytes::new(py, &data));
+    }
+
+    /// creates a state from the MessagePack encoding given by `to_msgpack`. It raises a
+    /// `StateLoadError` if the payload was written with another version, and a ValueError if it
+    /// is malformed
+    #[staticmethod]
+    #[pyo3(signature=(data, save_module=None))]
+    fn from_msgpack<'py>(data: &[u8], save_module: Option<Bound<'py, PyType>>) -> PyResult<Self> {
+        check_payload_version(data, MSGPACK_VERSION)?;
+        let payload: UltiTTTPayload = rmp_serde::from_slice(data).map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        if payload.board.len() != 81 {
+            return Err(PyValueError::new_err("malformed payload, wrong board size"));
+        }
+
+        let mut state = UltiTTTState::new();
+        for (c, &v) in payload.board.iter().enumerate() {
+            state.board[c / 9][c % 9] = v;
+        }
+        state.set_win_state(payload.win_state);
+        state.active_cell = payload.active_cell;
+        state.turn = payload.turn;
+        state.curr_pid = payload.curr_pid;
+        state.check_invariants().map_err(|reason| PyValueError::new_err(format!("malformed payload, {reason}")))?;
+
+        let ultittt_save: Py<PyType> = match save_module {
+            None => { Self::default_save_mod()}
+            Some(save_mod) => {save_mod.unbind()}
+        };
+        return Ok(Self::from_state(state, ultittt_save));
+    }
+
+    /// replaces the raw board and updates the wrapped state from it, with the won sub-boards
+    /// recomputed. It raises a ValueError if a cell is not 0, 1 or 2 or if the piece counts
+    /// cannot happen in a game
+    #[setter(_board)]
+    fn set_board(&mut self, board: Bound<'_, PyArray2<i64>>) -> PyResult<()> {
+        let array = board.readonly();
+        let array = array.as_array();
+        if array.dim() != UltiTTTState::DIMENSIONS {
+            return Err(PyValueError::new_err(format!("expected a {:?} board, got {:?}", UltiTTTState::DIMENSIONS, array.dim())));
+        }
+
+        // the board is validated and its won sub-boards recomputed as for a loaded one, but the
+        // turn, the player to move and the active cell stay the ones set on the state
+        let cells: [[i64; 9]; 9] = std::array::from_fn(|i| std::array::from_fn(|j| array[(i, j)]));
+        let derived = UltiTTTState::from_board(&cells).map_err(PyValueError::new_err)?;
+        self.state.board = cells;
+        self.state.set_win_state(derived.win_state());
+        self._board = GILOnceCell::new();
+        self.history = None;
+        return Ok(());
+    }
+
+    #[getter(_turn)]
+    fn get_raw_turn(&self) -> u32 { return self.state.turn }
+
+    #[setter(_turn)]
+    fn set_raw_turn(&mut self, turn: u32) { self.state.turn = turn; self.history = None }
+
+    #[getter(_curr_pid)]
+    fn get_raw_curr_pid(&self) -> u32 { return self.state.curr_pid }
+
+    #[setter(_curr_pid)]
+    fn set_raw_curr_pid(&mut self, curr_pid: u32) { self.state.curr_pid = curr_pid; self.history = None }
+
+    #[getter(_win_state)]
+    fn get_raw_win_state(&self) -> [i64; 9] { return self.state.win_state() }
+
+    #[setter(_win_state)]
+    fn set_raw_win_state(&mut self, win_state: [i64; 9]) { self.state.set_win_state(win_state); self.history = None }
+
+    #[getter(_active_cell)]
+    fn get_raw_active_cell(&self) -> i64 { return self.state.active_cell }
+
+    #[setter(_active_cell)]
+    fn set_raw_active_cell(&mut self, active_cell: i64) -> PyResult<()> {
+        if !(-1..=8).contains(&active_cell) {
+            return Err(PyValueError::new_err(format!("the active cell {active_cell} is not a sub-board from 0 to 8 or -1")));
+        }
+        self.state.active_cell = active_cell;
+        self.history = None;
+        return Ok(());
     }
 
     #[getter]
-    fn turn(&self) -> u32 { return self._turn }
+    fn turn(&self) -> u32 { return self.state.turn }
 
     #[getter]
-    fn curr_pid(&self) -> u32 { return self._curr_pid }
+    fn curr_pid(&self) -> u32 { return self.state.curr_pid }
 
+    /// the winner of the game, with the convention of `winner`. It is kept up to date by `play`
+    /// and the setters, so reading it is O(1)
     #[getter]
-    fn board(&self) -> &Py<PyArray2<i64>> { return &self._board }
-
-    fn __richcmp__<'py>(&self, py: Python<'py>, other: &Self, op: CompareOp) -> PyResult<Bound<'py, PyBool>> {
-        return match op {
-            CompareOp::Eq => {
-                let board_eq = std::iter::zip(
-                    self._board.bind(py).to_owned_array(),
-                    other._board.bind(py).to_owned_array()
-                ).all(|(a, b)| a == b);
-
-                let turn_eq = self._turn == other._turn;
-                let curr_pid_eq = self._curr_pid == other._curr_pid;
-                let active_cell_eq = self._active_cell == other._active_cell;
-                let win_state_eq = self._win_state == other._win_state;
-
-                let res = board_eq && active_cell_eq && turn_eq && curr_pid_eq && win_state_eq;
-                Ok(PyBool::new(py, res).to_owned())
-            },
-            _ => { Err(PyErr::new::<PyNotImplemented, _>("")) },
-        }
-    }
-}
+    fn meta_winner(&self) -> i64 { return self.state.winner() }
 
-///
-fn get_winner_of<'z, T: Iterator<Item = &'z i64> + Clone>(section: T) -> i64{
-    let g = section.collect_vec();
-
-    if !g.contains(&&0) {
-        return -1
-    }
-
-    let win_con = [
-        // diagonals
-        [g[0], g[4], g[8]],
-        [g[2], g[4], g[6]],
-        // rows
-        [g[0], g[1], g[2]],
-        [g[3], g[4], g[5]],
-        [g[6], g[7], g[8]],
-        // cols
-        [g[0], g[3], g[6]],
-        [g[1], g[4], g[7]],
-        [g[2], g[5], g[8]],
-    ];
-
-    let result = win_con.iter().find(|v| {
-        let val = v.iter().fold(-1, |a: i64, &&v| {
-            return if a == -1 { v } else if a == v { a } else { 0 }
-        });
-        return ![0, -1].contains(&val)
-    });
+    #[getter(_board)]
+    fn get_raw_board(&self, py: Python<'_>) -> PyResult<&Py<PyArray2<i64>>> { return self.board_py(py) }
 
-    return match result {
-        None => { 0 }
-        Some(v) => { *v[0] }
+    #[getter]
+    fn board(&self, py: Python<'_>) -> PyResult<&Py<PyArray2<i64>>> { return self.board_py(py) }
+
+    /// `==` and `!=` compare the states by value, while `<`, `<=`, `>` and `>=` compare them by
+    /// `score_difference`, so a list of states can be sorted by score. Two states can therefore
+    /// be neither smaller nor greater than each other without being equal. Any other type is
+    /// left to python with NotImplemented
+    fn __richcmp__<'py>(&self, py: Python<'py>, other: &Bound<'py, PyAny>, op: CompareOp) -> PyResult<PyObject> {
+        let Ok(other) = other.downcast::<Self>() else { return Ok(py.NotImplemented()); };
+        let other = other.borrow();
+        let (lhs, rhs) = (self.state.score_difference(), other.state.score_difference());
+        let res = match op {
+            CompareOp::Eq => self.state == other.state,
+            CompareOp::Ne => self.state != other.state,
+            CompareOp::Lt => lhs < rhs,
+            CompareOp::Le => lhs <= rhs,
+            CompareOp::Gt => lhs > rhs,
+            CompareOp::Ge => lhs >= rhs,
+        };
+        return Ok(PyBool::new(py, res).to_owned().into_any().unbind());
     }
-}
\ No newline at end of file
+}
+
+fn npy_error(e: impl std::fmt::Display) -> PyErr {
+    return PyIOError::new_err(e.to_string());
+}
@@ -1,29 +1,68 @@
 #![allow(non_snake_case)]
 #![allow(clippy::needless_return)]
 
+#[cfg(feature = "python")]
 extern crate numpy;
 extern crate itertools;
+pub mod Core;
+#[cfg(feature = "python")]
+mod Archive;
+#[cfg(feature = "python")]
 mod Avalam;
+#[cfg(feature = "python")]
 mod Checkers;
+#[cfg(feature = "python")]
+mod Game;
+#[cfg(feature = "python")]
+mod Transposition;
+#[cfg(feature = "python")]
 mod UltiTTT;
+#[cfg(feature = "python")]
 use pyo3::{pymodule, types::PyModule, PyResult};
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
 
+#[cfg(feature = "python")]
+use crate::Archive::{PyArchive, load_many, save_many};
+#[cfg(feature = "python")]
 use crate::Avalam::{RawAvalamState};
+#[cfg(feature = "python")]
 use crate::Checkers::{RawCheckersState};
+#[cfg(feature = "python")]
+use crate::Transposition::PyTranspositionTable;
+#[cfg(feature = "python")]
 use crate::UltiTTT::{RawUltiTTTState};
+#[cfg(feature = "python")]
+use crate::Game::{get_counters, metadata, reset_counters, IllegalMoveError, LegalMoves, PyGame, PyGameResult, rollouts, run_games, StateLoadError};
 
 /// The rust implementation of engines for multiple games
+#[cfg(feature = "python")]
 #[pymodule]
 #[pyo3(name="GameEngines")]
 fn RustEngine(m: &Bound<'_, PyModule>) -> PyResult<()> {
     Avalam(m)?;
     Checkers(m)?;
     UltiTTT(m)?;
+    m.add_class::<PyGame>()?;
+    m.add_class::<PyGameResult>()?;
+    m.add_class::<LegalMoves>()?;
+    m.add("IllegalMoveError", m.py().get_type::<IllegalMoveError>())?;
+    m.add("StateLoadError", m.py().get_type::<StateLoadError>())?;
+    m.add_function(wrap_pyfunction!(rollouts, m)?)?;
+    m.add_function(wrap_pyfunction!(run_games, m)?)?;
+    m.add_function(wrap_pyfunction!(get_counters, m)?)?;
+    m.add_function(wrap_pyfunction!(metadata, m)?)?;
+    m.add("__version__", env!("CARGO_PKG_VERSION"))?;
+    m.add_function(wrap_pyfunction!(reset_counters, m)?)?;
+    m.add_class::<PyArchive>()?;
+    m.add_function(wrap_pyfunction!(save_many, m)?)?;
+    m.add_function(wrap_pyfunction!(load_many, m)?)?;
+    m.add_class::<PyTranspositionTable>()?;
     Ok(())
 }
 
 /// The rust implementation of an Avalam engine
+#[cfg(feature = "python")]
 fn Avalam(main_module: &Bound<'_, PyModule>) -> PyResult<()> {
     let avalam_module = PyModule::new(main_module.py(), "Avalam")?;
     avalam_module.add_class::<RawAvalamState>()?;
@@ -31,6 +70,7 @@ fn Avalam(main_module: &Bound<'_, PyModule>) -> PyResult<()> {
 }
 
 /// The rust implementation of a Checkers engine
+#[cfg(feature = "python")]
 fn Checkers(main_module: &Bound<'_, PyModule>) -> PyResult<()> {
     let checkers_module = PyModule::new(main_module.py(), "Checkers")?;
     checkers_module.add_class::<RawCheckersState>()?;
@@ -39,9 +79,10 @@ fn Checkers(main_module: &Bound<'_, PyModule>) -> PyResult<()> {
 
 
 /// The rust implementation of an Ultimate Tic-tac-toe engine
+#[cfg(feature = "python")]
 fn UltiTTT(main_module: &Bound<'_, PyModule>) -> PyResult<()> {
     let ultiTTT_module = PyModule::new(main_module.py(), "UltiTTT")?;
     ultiTTT_module.add_class::<RawUltiTTTState>()?;
     main_module.add_submodule(&ultiTTT_module)
 
-}
\ No newline at end of file
+}
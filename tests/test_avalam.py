@@ -1,8 +1,17 @@
 from GameEngines.Avalam import BoardState as RustBoardState
 from GameEngines.Avalam.PythonEngine import BoardState as PyBoardState
+from GameEngines.Avalam.SaveModule import AvalamSave
+from GameEngines.Avalam.PythonEngine import utils
+from GameEngines import IllegalMoveError
+import GameEngines
 
+import copy
+import itertools
+import json
 import numpy as np
 import pytest
+import random
+import warnings
 
 
 rust_python = pytest.mark.parametrize(
@@ -10,6 +19,8 @@ rust_python = pytest.mark.parametrize(
     [RustBoardState, PyBoardState] if RustBoardState is not PyBoardState else [PyBoardState]
 )
 
+rust_only = pytest.mark.skipif(RustBoardState is PyBoardState, reason="the rust engine is not built")
+
 
 @rust_python
 def test_init(board_state):
@@ -23,6 +34,18 @@ def test_init(board_state):
     assert b.board[1, 0] == 0
 
 
+@rust_python
+@pytest.mark.parametrize("c_move", [((0, 0), (0, 1)), ((3, 8), (5, 8))])
+def test_play_illegal_move(board_state, c_move):
+    b = board_state()
+    with pytest.raises(IllegalMoveError) as info:
+        b.play(c_move)
+
+    assert isinstance(info.value, ValueError)
+    assert info.value.move == c_move
+    assert info.value.reason
+
+
 @rust_python
 def test_play_from_init_1(board_state):
     b = board_state()
@@ -95,3 +118,598 @@ def test_score(board_state):
 
     b = b.play(((3, 7), (3, 6)))
     assert b.score() == (23, 23)
+
+
+@rust_only
+@pytest.mark.parametrize("save", ["from_init_board_1", "from_init_board_2", "from_save_board_1", "from_save_board_2"])
+def test_legal_moves_match_python(save):
+    assert RustBoardState().get_legal_moves() == PyBoardState().get_legal_moves()
+
+    rust_b = RustBoardState.load(f"test_files/test_avalam/{save}.json")
+    py_b = PyBoardState.load(f"test_files/test_avalam/{save}.json")
+    assert rust_b.get_legal_moves() == py_b.get_legal_moves()
+
+
+@rust_only
+@pytest.mark.parametrize("seed", range(5))
+def test_legal_moves_match_python_random_game(seed):
+    rng = random.Random(seed)
+    rust_b, py_b = RustBoardState(), PyBoardState()
+
+    while rust_b.winner() == 0:
+        moves = rust_b.get_legal_moves()
+        assert moves == py_b.get_legal_moves()
+
+        m = rng.choice(sorted(moves))
+        rust_b, py_b = rust_b.play(m), py_b.play(m)
+
+    assert rust_b.winner() == py_b.winner()
+
+
+@rust_only
+def test_npy_round_trip(tmp_path):
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    b.save_board_npy(tmp_path / "board.npy", tmp_path / "ratios.npy")
+
+    assert np.array_equal(np.load(tmp_path / "board.npy"), b.board)
+    assert np.array_equal(np.load(tmp_path / "ratios.npy"), b.ratios)
+
+    loaded = RustBoardState.load_board_npy(tmp_path / "board.npy", tmp_path / "ratios.npy")
+    assert np.array_equal(loaded.board, b.board)
+    assert np.array_equal(loaded.ratios, b.ratios)
+    assert loaded.get_legal_moves() == b.get_legal_moves()
+
+
+@rust_only
+def test_msgpack_round_trip():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    data = b.to_msgpack()
+
+    assert isinstance(data, bytes)
+    assert RustBoardState.from_msgpack(data) == b
+
+
+@rust_only
+def test_fingerprint():
+    b = RustBoardState()
+    assert b.fingerprint() == RustBoardState().fingerprint()
+    assert b.fingerprint() == b.copy().fingerprint()
+    assert b.fingerprint() != b.play(((3, 8), (3, 7))).fingerprint()
+
+
+@rust_only
+def test_legal_moves_len_contains():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    moves = b.get_legal_moves()
+    legal = b.legal_moves()
+
+    assert len(legal) == len(moves)
+    assert all(m in legal for m in moves)
+    assert ((0, 0), (0, 1)) not in legal
+    assert ((3, 8), (9, 8)) not in legal
+    assert "not a move" not in legal
+    assert set(legal) == moves
+
+
+@rust_only
+def test_sort_by_score():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    children = sorted(b.play(m) for m in b.get_legal_moves())
+
+    diffs = [c.score_difference() for c in children]
+    assert diffs == sorted(diffs)
+    assert diffs[0] == children[0].score()[0] - children[0].score()[1]
+
+    # one tower of each player on either side of a gap: the scores are equal, the states are not
+    left, right = first_row(1, 0, -1), first_row(-1, 0, 1)
+    assert left.score_difference() == right.score_difference() == 0
+    assert left <= right and left >= right
+    assert not left < right and not left > right
+    assert left != right and not left == right
+    assert left == first_row(1, 0, -1) and not left != first_row(1, 0, -1)
+
+
+@rust_only
+def test_compare_other_types():
+    b = RustBoardState()
+    assert b != 0 and not b == "board"
+    with pytest.raises(TypeError):
+        b < 0
+
+
+@rust_only
+def test_dict_round_trip():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    data = json.loads(json.dumps(b.to_dict()))
+
+    assert data["board"] == b.board.tolist()
+    assert data["ratios"] == b.ratios.tolist()
+    assert RustBoardState.from_dict(data) == b
+
+
+@rust_only
+def test_lazy_copy():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    board = b.board
+    c = b.lazy_copy()
+
+    assert c == b
+    assert c.board is board
+    # the ratios were not built before the copy, so each state builds its own
+    assert c.ratios is not b.ratios and np.array_equal(c.ratios, b.ratios)
+    m = sorted(b.get_legal_moves())[0]
+    assert c.play(m) == b.play(m)
+    assert c == b
+
+
+@rust_only
+def test_top_colors():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    colors = b.top_colors()
+
+    assert colors.dtype == np.int8
+    assert np.array_equal(colors, np.sign(b.board))
+
+
+@rust_only
+def test_stable_towers():
+    b = RustBoardState()
+    assert b.stable_towers() == (0, 0)
+
+    while b.winner() == 0:
+        stable = b.stable_towers()
+        assert stable[0] <= b.score()[0] and stable[1] <= b.score()[1]
+        b = b.play(sorted(b.get_legal_moves())[0])
+        assert all(s >= p for s, p in zip(b.stable_towers(), stable))
+
+    assert b.stable_towers() == b.score()
+
+
+def first_row(*towers):
+    board = [[0] * 9 for _ in range(9)]
+    board[0][:len(towers)] = towers
+    return RustBoardState.from_board(board)
+
+
+@rust_only
+def test_solve_finished():
+    # the two towers are not neighbours, so the game is over on a tie
+    b = first_row(1, 0, -1)
+    assert b.winner() == -1
+    assert b.solve(0) == -1
+
+
+@rust_only
+def test_solve_win_in_one():
+    # player 1 stacks their tower on the one of player 2, and no move is left
+    b = first_row(1, -1)
+    assert b.curr_pid == 1
+    assert b.solve(0) is None
+    assert b.solve(1) == 1
+
+
+@rust_only
+def test_solve_win_in_two():
+    # player 2 stacks one of their towers on the one of player 1, which leaves player 1 without any
+    # tower for the last merge. Moving the tower of player 1 isolates the third one for a tie
+    b = first_row(-1, 1, -1)
+    assert b.curr_pid == 2
+    assert b.solve(1) is None
+    assert b.solve(2) == 2
+
+
+@rust_only
+def test_solve_lost_position():
+    # player 2 has no tower and no merge can give them one
+    b = first_row(1, 1, 1)
+    assert b.curr_pid == 2
+    assert b.solve(1) is None
+    assert b.solve(2) == 1
+
+
+@rust_only
+def test_solve_limits():
+    b = RustBoardState()
+    with pytest.raises(ValueError):
+        b.solve(6)
+
+    assert b.solve(3, max_moves=300, max_nodes=1000) is None
+    # the win is found after 4 states: the root, its first child and the two children of that one
+    assert first_row(-1, 1, -1).solve(2, max_nodes=3) is None
+    assert first_row(-1, 1, -1).solve(2, max_nodes=4) == 2
+
+
+@rust_only
+def test_movable_count():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    origins = {origin for origin, _ in b.get_legal_moves()}
+
+    expected = (
+        sum(1 for o in origins if b.board[o] > 0),
+        sum(1 for o in origins if b.board[o] < 0),
+    )
+    assert b.movable_count() == expected
+    assert tuple(m + s for m, s in zip(b.movable_count(), b.stable_towers())) == b.score()
+
+
+@rust_only
+def test_save_schema_version(tmp_path):
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    b.save(str(tmp_path / "board.json"))
+
+    data = json.loads((tmp_path / "board.json").read_text())
+    assert data["version"] == RustBoardState.SCHEMA_VERSION
+    assert RustBoardState.load(str(tmp_path / "board.json")) == b
+
+    data["version"] = RustBoardState.SCHEMA_VERSION + 1
+    (tmp_path / "board.json").write_text(json.dumps(data))
+    with pytest.raises(ValueError):
+        RustBoardState.load(str(tmp_path / "board.json"))
+
+
+@rust_only
+def test_custom_save_module(tmp_path):
+    calls = []
+
+    class RecordingSave(AvalamSave):
+        @staticmethod
+        def save_state(file, state, version=None):
+            calls.append("save")
+            AvalamSave.save_state(file, state, version)
+
+        @staticmethod
+        def load_state(file, state_type, version=None):
+            calls.append("load")
+            return AvalamSave.load_state(file, state_type, version)
+
+    b = RustBoardState(RecordingSave)
+    assert b._save_mod is RecordingSave
+    assert b.play(((3, 8), (3, 7)))._save_mod is RecordingSave
+
+    b.save(str(tmp_path / "board.json"))
+    assert RustBoardState.load(str(tmp_path / "board.json"), RecordingSave) == b
+    assert calls == ["save", "load"]
+
+
+@rust_only
+def test_save_module_without_version(tmp_path):
+    # save modules written before the schema versioning do not take a `version` argument
+    class LegacySave(AvalamSave):
+        @staticmethod
+        def save_state(file, state):
+            AvalamSave.save_state(file, state)
+
+        @staticmethod
+        def load_state(file, state_type):
+            return AvalamSave.load_state(file, state_type)
+
+    b = RustBoardState(LegacySave)
+    with pytest.warns(UserWarning, match="save_state .* does not take a `version`"):
+        b.save(str(tmp_path / "board.json"))
+
+    assert "version" not in json.loads((tmp_path / "board.json").read_text())
+    with pytest.warns(UserWarning, match="load_state .* is not checked"):
+        assert RustBoardState.load(str(tmp_path / "board.json"), LegacySave) == b
+
+    # the signatures are only read on the first call of each module, which is the only warning
+    with warnings.catch_warnings():
+        warnings.simplefilter("error")
+        b.save(str(tmp_path / "board.json"))
+        assert RustBoardState.load(str(tmp_path / "board.json"), LegacySave) == b
+        RustBoardState().save(str(tmp_path / "versioned.json"))
+        assert RustBoardState.load(str(tmp_path / "versioned.json")) == RustBoardState()
+
+
+@rust_only
+def test_legal_moves_array():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    moves = b.legal_moves_array()
+
+    assert moves.shape == (len(b.get_legal_moves()), 4)
+    assert {((o_r, o_c), (d_r, d_c)) for o_r, o_c, d_r, d_c in moves.tolist()} == b.get_legal_moves()
+
+
+@rust_only
+def test_is_symmetric_to():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    assert b.is_symmetric_to(b)
+
+    rotated = RustBoardState.from_dict({
+        "board": np.rot90(b.board).tolist(),
+        "ratios": np.rot90(b.ratios, axes=(1, 2)).tolist(),
+        "turn": b.turn,
+        "curr_pid": b.curr_pid,
+    })
+    assert rotated.is_symmetric_to(b) and b.is_symmetric_to(rotated)
+
+    swapped = rotated.to_dict()
+    swapped["ratios"] = swapped["ratios"][::-1]
+    assert not RustBoardState.from_dict(swapped).is_symmetric_to(b)
+
+
+@rust_only
+@pytest.mark.parametrize("seed", range(3))
+def test_incremental_score(seed):
+    rng = random.Random(seed)
+    b = RustBoardState()
+    while b.winner() == 0:
+        assert b.score() == (int(np.sum(b.board > 0)), int(np.sum(b.board < 0)))
+        b = b.play(rng.choice(sorted(b.get_legal_moves())))
+
+    assert b.score() == (int(np.sum(b.board > 0)), int(np.sum(b.board < 0)))
+
+
+@rust_only
+def test_from_board_validation():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    loaded = RustBoardState.from_board(b.board, b.ratios)
+    assert np.array_equal(loaded.board, b.board) and np.array_equal(loaded.ratios, b.ratios)
+    assert np.array_equal(RustBoardState.from_board(b.board.tolist()).board, b.board)
+
+    board = b.board.copy()
+    board[2, 3] = 6
+    with pytest.raises(ValueError, match=r"\(2, 3\)"):
+        RustBoardState.from_board(board)
+
+    ratios = b.ratios.copy()
+    i, j = map(int, np.argwhere(b.board != 0)[0])
+    ratios[0, i, j] += 1
+    with pytest.raises(ValueError, match=rf"\({i}, {j}\)"):
+        RustBoardState.from_board(b.board, ratios)
+
+
+def brute_force_moves(board):
+    """ reference move generation, checking the 8 neighbours of every tower with explicit bounds """
+    moves = set()
+    for i, j in itertools.product(range(9), range(9)):
+        if board[i][j] == 0:
+            continue
+        for di, dj in itertools.product((-1, 0, 1), (-1, 0, 1)):
+            ni, nj = i + di, j + dj
+            if (di, dj) == (0, 0) or not (0 <= ni < 9 and 0 <= nj < 9):
+                continue
+            if board[ni][nj] != 0 and abs(board[i][j]) + abs(board[ni][nj]) <= 5:
+                moves.add(((i, j), (ni, nj)))
+    return moves
+
+
+def edge_heavy_boards():
+    """ the initial board and random boards filled up to their edges, row and column 0 included """
+    boards = [utils.board_setup()[0]]
+    rng = random.Random(0)
+    for density in (0.5, 0.8, 1.0):
+        board = np.array([
+            [rng.choice((-1, 1)) * rng.randint(1, 4) if rng.random() < density else 0 for _ in range(9)]
+            for _ in range(9)
+        ], dtype=np.int64)
+        board[0, :] = np.where(board[0, :] == 0, 1, board[0, :])
+        board[:, 0] = np.where(board[:, 0] == 0, -1, board[:, 0])
+        boards.append(board)
+    return boards
+
+
+@pytest.mark.parametrize("board", edge_heavy_boards())
+def test_gen_moves_match_brute_force(board):
+    assert utils.gen_moves(board) == brute_force_moves(board)
+
+
+@rust_python
+@pytest.mark.parametrize("board", edge_heavy_boards())
+def test_legal_moves_match_brute_force(board_state, board):
+    b = board_state()
+    b._board = board.copy()
+    assert b.get_legal_moves() == brute_force_moves(board)
+
+
+@rust_only
+def test_towers_dict():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    towers = b.towers_dict()
+
+    assert len(towers) == sum(b.score())
+    for (i, j), (sign, height) in towers.items():
+        assert b.board[i, j] == sign * height and height > 0
+
+
+@rust_only
+def test_set_membership():
+    initial = RustBoardState()
+    played = initial.play(((0, 3), (1, 3)))
+    rebuilt = RustBoardState.from_board(initial.board.tolist())
+
+    states = {initial, RustBoardState(), rebuilt, played, played.copy()}
+
+    assert len(states) == 2
+    assert RustBoardState() in states
+    assert {played: 1}[RustBoardState.from_dict(played.to_dict())] == 1
+
+
+@rust_only
+def test_deepcopy():
+    b = RustBoardState()
+    board, ratios = b.board.copy(), b.ratios.copy()
+    c = copy.deepcopy(b)
+
+    moved = c.board.copy()
+    moved[3, 3], moved[3, 2] = 2, 0
+    c._board = moved
+    c._turn = 1
+
+    assert np.array_equal(b.board, board)
+    assert np.array_equal(b.ratios, ratios)
+    assert b == RustBoardState()
+    assert not (c == b)
+
+
+@rust_only
+def test_copy_protocol():
+    b = RustBoardState()
+    c = copy.copy(b)
+    assert c == b and c.board is not b.board and c.ratios is not b.ratios
+
+    c._curr_pid = 2
+    assert b.curr_pid == 1
+    assert c.play(((0, 3), (1, 3))).board.tolist() == b.play(((0, 3), (1, 3))).board.tolist()
+
+
+@rust_only
+def test_moves_to():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    moves = b.get_legal_moves()
+    occupied = [(i, j) for i in range(9) for j in range(9) if b.board[i, j] != 0]
+
+    for dest in occupied:
+        assert b.moves_to(dest) == {m for m in moves if m[1] == dest}
+
+    empty = next((i, j) for i in range(9) for j in range(9) if b.board[i, j] == 0)
+    with pytest.raises(ValueError):
+        b.moves_to(empty)
+
+
+@rust_only
+def test_check_invariants():
+    rng = random.Random(7)
+    b = RustBoardState()
+    b.check_invariants()
+    while b.get_legal_moves():
+        b = b.play(rng.choice(sorted(b.get_legal_moves())))
+        b.check_invariants()
+
+    broken = RustBoardState()
+    ratios = broken.ratios.copy()
+    ratios[:, 3, 3] = 0
+    broken._ratios = ratios
+    with pytest.raises(ValueError, match=r"\(3, 3\)"):
+        broken.check_invariants()
+
+
+@rust_only
+def test_is_legal():
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    moves = b.get_legal_moves()
+    cells = [(i, j) for i in range(9) for j in range(9)]
+
+    for origin in cells:
+        for dest in cells:
+            assert b.is_legal(origin, dest) == ((origin, dest) in moves)
+    assert not b.is_legal((0, 0), (9, 9))
+
+
+@rust_only
+def test_fen_round_trip():
+    rng = random.Random(11)
+    b = RustBoardState()
+    assert b.fen().split("/")[0] == "2+10-015"
+    assert b.fen().endswith(" 1 0")
+
+    while b.get_legal_moves():
+        assert RustBoardState.from_fen(b.fen()) == b
+        b = b.play(rng.choice(sorted(b.get_legal_moves())))
+    assert RustBoardState.from_fen(b.fen()) == b
+
+
+@rust_only
+@pytest.mark.parametrize("fen", [
+    "", "9/9/9/9/9/9/9/9 1 0", "8/9/9/9/9/9/9/9/9 1 0", "+6/9/9/9/9/9/9/9/9 1 0", "+33/9/9/9/9/9/9/9/9 1 0",
+    "-10/9/9/9/9/9/9/9/9 1 0", "+1/9/9/9/9/9/9/9/9 1 0", "9/9/9/9/9/9/9/9/9 3 0", "9/9/9/9/9/9/9/9/9 1 x",
+])
+def test_from_fen_invalid(fen):
+    with pytest.raises(ValueError):
+        RustBoardState.from_fen(fen)
+
+
+@rust_only
+def test_mobility():
+    b = RustBoardState()
+    assert b.mobility() == 292
+    assert sum(b.mobility_by_player()) == 292
+
+    b = RustBoardState.load("test_files/test_avalam/from_save_board_1.json")
+    moves = b.get_legal_moves()
+    assert b.mobility() == len(moves)
+    assert b.mobility_by_player() == (
+        sum(1 for (origin, _) in moves if b.board[origin] > 0),
+        sum(1 for (origin, _) in moves if b.board[origin] < 0),
+    )
+
+
+@rust_only
+def test_arrays_are_read_only():
+    b = RustBoardState()
+    with pytest.raises(ValueError):
+        b.board[3, 3] = 5
+    with pytest.raises(ValueError):
+        b.ratios[0, 3, 3] = 5
+    assert b == RustBoardState()
+
+    board = b.board.copy()
+    board[3, 3] = 0
+    b._board = board
+    assert b.board[3, 3] == 0 and not b.board.flags.writeable
+    assert board.flags.writeable and b.board is not board
+    assert ((3, 3), (3, 2)) not in b.get_legal_moves()
+
+
+@rust_only
+@pytest.mark.parametrize("attr, value", [
+    ("_board", np.zeros((10, 10), dtype=np.int64)),
+    ("_board", np.full((9, 9), 6, dtype=np.int64)),
+    ("_ratios", np.zeros((2, 10, 9), dtype=np.int64)),
+    ("_ratios", np.full((2, 9, 9), -1, dtype=np.int64)),
+])
+def test_setters_reject_invalid_arrays(attr, value):
+    b = RustBoardState()
+    with pytest.raises(ValueError):
+        setattr(b, attr, value)
+    assert b == RustBoardState()
+
+
+def _corrupt(data, key, index, value):
+    data = json.loads(json.dumps(data))
+    if index is None:
+        data[key] = value
+    else:
+        target = data[key]
+        for k in index[:-1]:
+            target = target[k]
+        target[index[-1]] = value
+    return data
+
+
+@rust_only
+@pytest.mark.parametrize("key, index, value", [
+    ("board", (3, 3), 300),
+    ("board", (3, 3), 6),
+    ("ratios", (0, 3, 3), 256),
+    ("ratios", (0, 3, 3), -1),
+    ("ratios", (1, 3, 3), 1),
+    ("curr_pid", None, 3),
+])
+def test_from_dict_invalid(key, index, value):
+    data = _corrupt(RustBoardState().to_dict(), key, index, value)
+    with pytest.raises(ValueError):
+        RustBoardState.from_dict(data)
+
+
+@rust_only
+def test_from_msgpack_future_version():
+    # a payload of a later version, with one more field after the current ones
+    data = bytearray(RustBoardState().to_msgpack())
+    data[0], data[1] = 0x96, 0x02
+    data += b"\xc0"
+
+    with pytest.raises(GameEngines.StateLoadError, match="version 2"):
+        RustBoardState.from_msgpack(bytes(data))
+
+
+@rust_only
+@pytest.mark.parametrize("offset, value", [(5 + 3 * 9 + 3, 0x7f), (5 + 3 * 9 + 3, 0x06), (-1, 0x03)])
+def test_from_msgpack_invalid(offset, value):
+    # the payload is [version, board, ratios, turn, curr_pid] with the 81 cells of the board
+    # as fixints after the array header, and the player to move last
+    data = bytearray(RustBoardState().to_msgpack())
+    assert data[:5] == b"\x95\x01\xdc\x00\x51"
+
+    data[offset] = value
+    with pytest.raises(ValueError):
+        RustBoardState.from_msgpack(bytes(data))
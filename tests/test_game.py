@@ -1,3 +1,4 @@
+import GameEngines
 from GameEngines import Game, RandomPlayer
 from GameEngines.Avalam import BoardState as Avalam
 from GameEngines.UltiTTT import BoardState as UltiTTT
@@ -11,6 +12,8 @@ all_games = pytest.mark.parametrize(
     [Avalam, UltiTTT]
 )
 
+rust_only = pytest.mark.skipif(not hasattr(GameEngines, "GameKind"), reason="the rust engine is not built")
+
 
 @all_games
 def test_game_init(engine):
@@ -60,3 +63,225 @@ def test_branch(engine):
     assert branch.time_data == game.time_data[:4]
     assert len(branch.time_data) == len(branch.history) - 1
     assert ori_len == len(game.history)
+
+
+@rust_only
+@pytest.mark.parametrize("kind", ["Avalam", "UltiTTT"])
+def test_game_kind_dispatch(kind):
+    game = getattr(GameEngines.GameKind, kind)
+    state = game.new_state()
+    assert state == (Avalam if kind == "Avalam" else UltiTTT)()
+
+    while game.winner(state) == 0:
+        moves = game.legal_moves(state)
+        assert moves == state.get_legal_moves()
+        state = game.play(state, sorted(moves)[0])
+
+    assert game.winner(state) == state.winner()
+
+
+@rust_only
+def test_rollouts():
+    states = [Avalam(), UltiTTT(), Avalam().play(((3, 8), (3, 7)))]
+
+    results = GameEngines.rollouts(states, 42)
+    assert results == GameEngines.rollouts(states, 42)
+    assert len(results) == len(states)
+    assert all(winner != 0 for winner, _ in results)
+
+
+@rust_only
+def test_archive_round_trip(tmp_path):
+    states = [Avalam()]
+    while states[-1].winner() == 0:
+        states.append(states[-1].play(sorted(states[-1].get_legal_moves())[0]))
+
+    path = str(tmp_path / "games.bin")
+    GameEngines.save_many(states, path)
+    archive = GameEngines.load_many(path)
+
+    assert len(archive) == len(states)
+    assert archive[-1] == states[-1]
+    assert list(archive) == states
+    with pytest.raises(IndexError):
+        archive[len(states)]
+
+    with pytest.raises(ValueError):
+        GameEngines.save_many([Avalam(), UltiTTT()], path)
+
+
+@rust_only
+@pytest.mark.parametrize("engine, index, value", [
+    (Avalam, 3 * 9 + 3, 0x7f),
+    (Avalam, 81 + 3 * 9 + 3, 0x09),
+    (Avalam, 3 * 81 + 4, 0x03),
+    (UltiTTT, 0, 0x05),
+    (UltiTTT, 90, 0x09),
+])
+def test_from_bytes_invalid(engine, index, value):
+    data = bytearray(engine().to_bytes())
+    data[index] = value
+    with pytest.raises(ValueError):
+        engine.from_bytes(bytes(data))
+
+    with pytest.raises(ValueError):
+        engine.from_bytes(bytes(data[:-1]))
+
+
+@rust_only
+def test_archive_corrupt_record(tmp_path):
+    path = tmp_path / "games.bin"
+    GameEngines.save_many([Avalam(), Avalam()], str(path))
+
+    # the second record starts after the 16 bytes header and the first record
+    data = bytearray(path.read_bytes())
+    data[16 + len(Avalam().to_bytes())] = 0x7f
+    path.write_bytes(bytes(data))
+
+    archive = GameEngines.load_many(str(path))
+    assert archive[0] == Avalam()
+    with pytest.raises(ValueError):
+        archive[1]
+    with pytest.raises(ValueError):
+        list(archive)
+
+
+@rust_only
+@pytest.mark.parametrize("state, c_move", [
+    (Avalam(), ((0, 0), (0, 1))),
+    (Avalam(), ((3, 8), (5, 8))),
+    (UltiTTT().play(((1, 1), (0, 2))), ((1, 1), (0, 0))),
+])
+def test_illegal_move_error(state, c_move):
+    with pytest.raises(GameEngines.IllegalMoveError) as info:
+        state.play(c_move)
+
+    assert isinstance(info.value, ValueError)
+    assert info.value.move == c_move
+    assert info.value.reason
+
+    kind = GameEngines.GameKind.Avalam if isinstance(state, Avalam) else GameEngines.GameKind.UltiTTT
+    with pytest.raises(GameEngines.IllegalMoveError):
+        kind.play(state, c_move)
+
+
+@rust_only
+@pytest.mark.parametrize("engine", [Avalam, UltiTTT])
+def test_current_player(engine):
+    state = engine()
+    for pid in [1, 2, 1, 2]:
+        assert state.current_player() == state.curr_pid == pid
+        state = state.play(sorted(state.get_legal_moves())[0])
+
+
+@rust_only
+def test_transposition_table():
+    table = GameEngines.TranspositionTable()
+    assert table.get(42) is None
+
+    assert table.put(42, 3, 0.5, ((3, 8), (3, 7)))
+    assert table.get(42) == (3, 0.5, ((3, 8), (3, 7)))
+    assert table.put(42, 1, -1.0)
+    assert table.get(42) == (1, -1.0, None)
+    assert len(table) == 1
+
+
+@rust_only
+@pytest.mark.parametrize("capacity", [None, 16])
+def test_transposition_table_negative_hash(capacity):
+    table = GameEngines.TranspositionTable(capacity)
+    assert table.put(-5, 2, 0.25)
+    assert table.get(-5) == (2, 0.25, None)
+    assert table.get(2 ** 64 - 5) == (2, 0.25, None)
+
+    state = Avalam()
+    while hash(state) >= 0:
+        state = state.play(sorted(state.get_legal_moves())[0])
+    assert table.put(hash(state), 1, 1.0)
+    assert table.get(hash(state)) == (1, 1.0, None)
+
+    with pytest.raises(OverflowError):
+        table.get(2 ** 64)
+
+
+@rust_only
+def test_transposition_table_depth_preferred():
+    table = GameEngines.TranspositionTable(capacity=4)
+    assert table.put(1, 5, 1.0)
+    assert not table.put(5, 2, 0.0)
+    assert table.get(5) is None
+    assert table.put(5, 6, 0.0)
+    assert table.get(1) is None and table.get(5) == (6, 0.0, None)
+
+    table.clear()
+    assert len(table) == 0
+    with pytest.raises(ValueError):
+        GameEngines.TranspositionTable(capacity=0)
+
+
+@rust_only
+@pytest.mark.parametrize("kind", ["Avalam", "UltiTTT"])
+def test_run_games(kind):
+    game = getattr(GameEngines.GameKind, kind)
+    results = GameEngines.run_games(8, game, 7)
+
+    assert results == GameEngines.run_games(8, game, 7)
+    assert len(results) == 8
+    assert all(winner in (-1, 1, 2) and moves > 0 for winner, moves in results)
+
+
+@rust_only
+def test_counters():
+    GameEngines.reset_counters()
+    state = Avalam()
+    state.copy()
+    state.play(((3, 8), (3, 7))).lazy_copy()
+    GameEngines.GameKind.UltiTTT.play(UltiTTT(), ((1, 1), (1, 1)))
+
+    assert GameEngines.get_counters() == (2, 2)
+    GameEngines.reset_counters()
+    assert GameEngines.get_counters() == (0, 0)
+
+
+@rust_only
+def test_metadata():
+    data = GameEngines.metadata()
+    assert GameEngines.__version__ == data["version"]
+    assert data["games"]["Avalam"] == (9, 9)
+    assert data["games"]["UltiTTT"] == (9, 9) == UltiTTT().board.shape
+    assert data["games"]["Checkers"] == GameEngines.Checkers.BoardState().board.shape
+
+
+@rust_only
+@pytest.mark.parametrize("engine", [Avalam, UltiTTT])
+@pytest.mark.parametrize("content", ["", "{not json", '{"board": [1, 2]}'])
+def test_state_load_error(engine, content, tmp_path):
+    path = tmp_path / "corrupt.json"
+    path.write_text(content)
+
+    with pytest.raises(GameEngines.StateLoadError) as info:
+        engine.load(str(path))
+
+    assert isinstance(info.value, ValueError)
+    assert info.value.__cause__ is not None
+    assert "corrupt.json" in str(info.value)
+
+    with pytest.raises(GameEngines.StateLoadError):
+        engine.load(str(tmp_path / "missing.json"))
+
+
+@rust_only
+def test_game_result():
+    assert Avalam().result() == GameEngines.GameResult.Ongoing
+    assert UltiTTT().result() == GameEngines.GameResult.Ongoing
+
+    expected = {
+        -1: GameEngines.GameResult.Draw, 1: GameEngines.GameResult.Player1, 2: GameEngines.GameResult.Player2
+    }
+    for engine in engines:
+        state = engine()
+        while state.get_legal_moves():
+            state = state.play(sorted(state.get_legal_moves())[0])
+            if state.winner() == 0:
+                assert state.result() == GameEngines.GameResult.Ongoing
+        assert state.result() == expected[state.winner()]
@@ -1,14 +1,23 @@
 from GameEngines.UltiTTT import BoardState as RustBoardState
 from GameEngines.UltiTTT.PythonEngine import BoardState as PyBoardState
+from GameEngines import IllegalMoveError
+import GameEngines
 
+import copy
+import itertools
+import json
+from pathlib import Path
 import numpy as np
 import pytest
+import random
 
 rust_python = pytest.mark.parametrize(
     "board_state",
     [RustBoardState, PyBoardState] if RustBoardState is not PyBoardState else [PyBoardState]
 )
 
+rust_only = pytest.mark.skipif(RustBoardState is PyBoardState, reason="the rust engine is not built")
+
 
 @rust_python
 def test_init(board_state):
@@ -19,6 +28,18 @@ def test_init(board_state):
     assert np.sum(b.board) == 0
 
 
+@rust_python
+@pytest.mark.parametrize("c_move", [((1, 1), (1, 1)), ((0, 0), (0, 0))])
+def test_play_illegal_move(board_state, c_move):
+    b = board_state().play(((1, 1), (0, 2)))
+    with pytest.raises(IllegalMoveError) as info:
+        b.play(c_move)
+
+    assert isinstance(info.value, ValueError)
+    assert info.value.move == c_move
+    assert info.value.reason
+
+
 @rust_python
 def test_play_from_init_1(board_state):
     b = board_state()
@@ -83,4 +104,524 @@ def test_winner(board_state):
     assert ref_board.winner() == 1
 
     ref_board = board_state.load("test_files/test_ultittt/winner_board_3.json")
-    assert ref_board.winner() == -1
\ No newline at end of file
+    assert ref_board.winner() == -1
+
+
+@rust_only
+def test_npy_round_trip(tmp_path):
+    b = RustBoardState.load("test_files/test_ultittt/from_init_board_2.json")
+    b.save_board_npy(tmp_path / "board.npy")
+
+    assert np.array_equal(np.load(tmp_path / "board.npy"), b.board)
+
+    loaded = RustBoardState.load_board_npy(tmp_path / "board.npy")
+    assert np.array_equal(loaded.board, b.board)
+    assert loaded._win_state == b._win_state
+    assert loaded.winner() == b.winner()
+
+
+@rust_only
+@pytest.mark.parametrize("cell, value", [((0, 0), 3), ((0, 0), -1), ((0, 0), 2)])
+def test_npy_invalid_board(tmp_path, cell, value):
+    board = np.zeros((9, 9), dtype=np.int64)
+    board[cell] = value
+    np.save(tmp_path / "board.npy", board)
+
+    with pytest.raises(ValueError):
+        RustBoardState.load_board_npy(tmp_path / "board.npy")
+
+
+@rust_only
+def test_set_board_validates_and_rebuilds():
+    b = RustBoardState()
+    board = np.zeros((9, 9), dtype=np.int64)
+    board[0, :3] = 1
+    board[4, :2] = 2
+
+    b._board = board
+    assert b._win_state == [1] + [0] * 8
+    assert b.score() == (1, 0) and b.winner() == 0
+    legal = b.get_legal_moves()
+    assert len(legal) == 81 - 9 - 2
+    assert all(sup_cell != (0, 0) for sup_cell, _ in legal)
+
+    for value in (3, -1):
+        bad = board.copy()
+        bad[8, 8] = value
+        with pytest.raises(ValueError):
+            b._board = bad
+    bad = board.copy()
+    bad[8, :3] = 2
+    with pytest.raises(ValueError):
+        b._board = bad
+    assert np.array_equal(b.board, board)
+
+
+@rust_only
+def test_msgpack_round_trip():
+    b = RustBoardState.load("test_files/test_ultittt/from_init_board_2.json")
+    data = b.to_msgpack()
+
+    assert isinstance(data, bytes)
+    assert RustBoardState.from_msgpack(data) == b
+
+
+@rust_only
+def test_fingerprint():
+    b = RustBoardState()
+    assert b.fingerprint() == RustBoardState().fingerprint()
+    assert b.fingerprint() == b.copy().fingerprint()
+    assert b.fingerprint() != b.play(((1, 1), (1, 1))).fingerprint()
+
+
+@rust_only
+def test_legal_moves_len_contains():
+    b = RustBoardState().play(((1, 1), (0, 2)))
+    legal = b.legal_moves()
+
+    assert len(legal) == len(b.get_legal_moves()) == 9
+    assert all(m in legal for m in b.get_legal_moves())
+    assert ((1, 1), (0, 0)) not in legal
+    assert ((0, 2), (3, 0)) not in legal
+    assert len(RustBoardState().legal_moves()) == 81
+
+
+@rust_only
+def test_legal_moves_iter():
+    b = RustBoardState().play(((1, 1), (0, 2)))
+    it = iter(b.legal_moves())
+
+    assert next(it) == ((0, 2), (0, 0))
+    assert set(it) == b.get_legal_moves() - {((0, 2), (0, 0))}
+    assert list(it) == []
+    assert set(RustBoardState().legal_moves()) == RustBoardState().get_legal_moves()
+
+
+@rust_only
+def test_dict_round_trip():
+    b = RustBoardState().play(((1, 1), (0, 2))).play(((0, 2), (1, 1)))
+    data = json.loads(json.dumps(b.to_dict()))
+
+    assert data["board"] == b.board.tolist()
+    assert data["active_cell"] == 4
+    assert RustBoardState.from_dict(data) == b
+
+
+def near_terminal(curr_pid):
+    board = [[0] * 9 for _ in range(9)]
+    board[0][:3] = [1, 1, 1]
+    board[1][:3] = [1, 1, 1]
+    board[2][:2] = [1, 1]
+    board[3][:3] = [2, 2, 2]
+    board[4][:3] = [2, 2, 2]
+    board[5][6:8] = [2, 2]
+    return RustBoardState.from_dict({
+        "board": board,
+        "win_state": [1, 1, 0, 2, 2, 0, 0, 0, 0],
+        "active_cell": 2,
+        "turn": 16,
+        "curr_pid": curr_pid,
+    })
+
+
+@rust_only
+def test_solve_forced_win():
+    b = near_terminal(1)
+    assert b.solve(0) is None
+    assert b.solve(1) == 1
+
+    end = b.play(((0, 2), (0, 2)))
+    assert end.winner() == 1
+    assert end.solve(0) == 1
+
+
+@rust_only
+def test_solve_blocked_win():
+    # player 2 must play in sub-board 2, so they cannot complete sub-board 5. Taking its last cell of
+    # the top row sends player 1 back to sub-board 2, where they cannot win in one move either
+    b = near_terminal(2)
+    assert b.solve(1) is None
+    assert b.solve(2) is None
+
+
+@rust_only
+def test_solve_forced_reply():
+    # the only move of player 2 fills sub-board 6 without a line and sends player 1 to sub-board 2,
+    # where they complete the top row of the meta-board
+    board = [[0] * 9 for _ in range(9)]
+    board[0][:3] = [1, 1, 1]
+    board[1][:3] = [1, 1, 1]
+    board[2][:2] = [1, 1]
+    board[3][:3] = [2, 2, 2]
+    board[4][:3] = [2, 2, 2]
+    board[6] = [2, 1, 0, 1, 1, 2, 2, 2, 1]
+    board[7][4] = 2
+    b = RustBoardState.from_dict({
+        "board": board,
+        "win_state": [1, 1, 0, 2, 2, 0, 0, 0, 0],
+        "active_cell": 6,
+        "turn": 23,
+        "curr_pid": 2,
+    })
+
+    assert b.get_legal_moves() == {((2, 0), (0, 2))}
+    assert b.solve(1) is None
+    assert b.solve(2) == 1
+
+    reply = b.play(((2, 0), (0, 2)))
+    assert reply.winner() == 0
+    assert reply.solve(1) == 1
+
+
+@rust_only
+def test_sub_board_counts():
+    b = near_terminal(1)
+    assert b.sub_board_counts(2) == (2, 0, 7)
+    assert b.sub_board_counts(5) == (0, 2, 7)
+    assert b.sub_board_counts(8) == (0, 0, 9)
+
+    with pytest.raises(IndexError):
+        b.sub_board_counts(9)
+
+
+@rust_only
+def test_save_schema_version(tmp_path):
+    b = RustBoardState().play(((1, 1), (0, 2)))
+    b.save(str(tmp_path / "board.json"))
+
+    data = json.loads((tmp_path / "board.json").read_text())
+    assert data["version"] == RustBoardState.SCHEMA_VERSION
+    assert RustBoardState.load(str(tmp_path / "board.json")) == b
+
+    data["version"] = RustBoardState.SCHEMA_VERSION + 1
+    (tmp_path / "board.json").write_text(json.dumps(data))
+    with pytest.raises(ValueError):
+        RustBoardState.load(str(tmp_path / "board.json"))
+
+
+@rust_python
+def test_load_legacy_save(board_state, tmp_path):
+    ref = board_state.load("test_files/test_ultittt/winner_board_1.json")
+    data = json.loads(Path("test_files/test_ultittt/winner_board_1.json").read_text())
+    del data["win_state"], data["active_cell"]
+
+    (tmp_path / "legacy.json").write_text(json.dumps(data))
+    b = board_state.load(str(tmp_path / "legacy.json"))
+    assert b._win_state == ref._win_state
+    assert b._active_cell == -1
+
+    data["last_move"] = [[1, 1], [2, 2]]
+    (tmp_path / "legacy.json").write_text(json.dumps(data))
+    assert board_state.load(str(tmp_path / "legacy.json"))._active_cell == 8
+
+    del data["board"]
+    (tmp_path / "legacy.json").write_text(json.dumps(data))
+    with pytest.raises(ValueError):
+        board_state.load(str(tmp_path / "legacy.json"))
+
+
+@rust_only
+def test_legal_moves_array():
+    b = RustBoardState().play(((1, 1), (0, 2)))
+    moves = b.legal_moves_array()
+
+    assert moves.shape == (9, 4)
+    assert {((sup_r, sup_c), (sub_r, sub_c)) for sup_r, sup_c, sub_r, sub_c in moves.tolist()} == b.get_legal_moves()
+    assert RustBoardState().legal_moves_array().shape == (81, 4)
+
+
+def symmetric_variants(b):
+    """ returns the 8 rotations and reflections of a state, computed on the 9x9 grid of cells """
+    def to_grid(board):
+        # (sub-board, cell) to (row, col) of the whole grid, and back as it is an involution
+        return np.asarray(board).reshape((3, 3, 3, 3)).transpose(0, 2, 1, 3).reshape((9, 9))
+
+    variants = []
+    for k in range(4):
+        for flip in (False, True):
+            def f(a):
+                a = np.rot90(a, k)
+                return a.T if flip else a
+
+            data = b.to_dict()
+            data["board"] = to_grid(f(to_grid(data["board"]))).tolist()
+            data["win_state"] = f(np.reshape(data["win_state"], (3, 3))).flatten().tolist()
+            if data["active_cell"] != -1:
+                active = np.zeros((3, 3), dtype=int)
+                active.flat[data["active_cell"]] = 1
+                data["active_cell"] = int(np.argmax(f(active)))
+            variants.append(RustBoardState.from_dict(data))
+    return variants
+
+
+@rust_only
+def test_is_symmetric_to():
+    b = RustBoardState()
+    for m in [((0, 0), (0, 1)), ((0, 1), (2, 2)), ((2, 2), (1, 0)), ((1, 0), (0, 1))]:
+        b = b.play(m)
+
+    variants = symmetric_variants(b)
+    assert len({v.fingerprint() for v in variants}) == 8
+    for v in variants:
+        assert v.is_symmetric_to(b) and b.is_symmetric_to(v)
+
+    other = b.to_dict()
+    other["curr_pid"] = 3 - other["curr_pid"]
+    assert not RustBoardState.from_dict(other).is_symmetric_to(b)
+
+
+@rust_only
+def test_meta_winner():
+    b = RustBoardState()
+    assert b.meta_winner == 0
+    while b.winner() == 0:
+        b = b.play(sorted(b.get_legal_moves())[0])
+        assert b.meta_winner == b.winner() == RustBoardState._get_winner_of(b._win_state)
+
+    loaded = RustBoardState.load("test_files/test_ultittt/winner_board_1.json")
+    assert loaded.meta_winner == loaded.winner() == 2
+    assert RustBoardState.from_dict(loaded.to_dict()).meta_winner == 2
+
+
+@rust_only
+@pytest.mark.parametrize("active_cell", [-1, 0])
+def test_legal_moves_free(active_cell):
+    # sub-board 0 is decided, so forcing it leaves the next move free as well
+    data = near_terminal(1).to_dict()
+    data["active_cell"] = active_cell
+    b = RustBoardState.from_dict(data)
+
+    expected = {
+        ((i // 3, i % 3), (j // 3, j % 3))
+        for i in range(9) for j in range(9)
+        if data["win_state"][i] == 0 and data["board"][i][j] == 0
+    }
+    assert b.get_legal_moves() == expected
+    assert len(b.legal_moves()) == len(expected)
+    assert {((sup_r, sup_c), (sub_r, sub_c)) for sup_r, sup_c, sub_r, sub_c in b.legal_moves_array().tolist()} == expected
+
+
+@rust_only
+@pytest.mark.parametrize("win_state, draw, wins", [
+    ([1, 1, 0, 2, 2, 0, 0, 0, 0], False, (False, False)),
+    ([1, 2, 1, 1, 2, 2, 2, 1, -1], True, (False, False)),
+    ([1, 1, 1, 2, 2, -1, 2, -1, 2], False, (True, False)),
+])
+def test_is_draw(win_state, draw, wins):
+    data = near_terminal(1).to_dict()
+    data["win_state"] = win_state
+    b = RustBoardState.from_dict(data)
+
+    assert b.is_draw() == draw
+    assert (b.win_for(1), b.win_for(2)) == wins
+
+
+@rust_only
+@pytest.mark.parametrize("player", [0, -1, 3])
+def test_win_for_invalid_player(player):
+    with pytest.raises(ValueError):
+        RustBoardState().win_for(player)
+
+
+@rust_only
+def test_win_for_tied_line():
+    # the first column of the meta-board only holds tied sub-boards, which are no player's line
+    data = near_terminal(1).to_dict()
+    data["win_state"] = [-1, 1, 0, -1, 2, 0, -1, 0, 0]
+    b = RustBoardState.from_dict(data)
+
+    assert b.winner() == 0
+    assert not b.win_for(1) and not b.win_for(2)
+    with pytest.raises(ValueError):
+        b.win_for(-1)
+
+
+@rust_only
+def test_compare_other_types():
+    b = RustBoardState()
+    assert b != 0 and not b == "board"
+    assert not b != RustBoardState()
+    assert b != b.play(((1, 1), (1, 1)))
+    with pytest.raises(TypeError):
+        b < 0
+
+
+@rust_only
+def test_deepcopy():
+    b = RustBoardState()
+    c = copy.deepcopy(b)
+
+    board = c.board.copy()
+    board[0, 0] = 1
+    c._board = board
+    c._active_cell = 0
+
+    assert not b.board.any()
+    assert b == RustBoardState()
+    assert not (c == b)
+
+
+@rust_only
+@pytest.mark.parametrize("copy_fn", [copy.copy, copy.deepcopy])
+def test_copy_then_play(copy_fn):
+    b = RustBoardState().play(((1, 1), (0, 2)))
+    board = b.board.copy()
+    c = copy_fn(b)
+    assert c.board is not b.board
+
+    played = c.play(((0, 2), (1, 1)))
+    c._active_cell = -1
+
+    assert np.array_equal(b.board, board)
+    assert b._active_cell == 2
+    assert played.board[2, 4] == 2 and b.board[2, 4] == 0
+    assert c._save_mod is b._save_mod
+
+
+@rust_only
+def test_moves_played():
+    b = RustBoardState()
+    played = []
+    for c_move in [((1, 1), (0, 2)), ((0, 2), (1, 1)), ((1, 1), (2, 2)), ((2, 2), (0, 0))]:
+        b = b.play(c_move)
+        played.append(c_move)
+
+    assert b.moves_played() == played
+    assert b.copy().moves_played() == played
+    assert RustBoardState().moves_played() == []
+
+    rebuilt = RustBoardState.from_moves(b.moves_played())
+    assert rebuilt == b
+    assert rebuilt.moves_played() == played
+
+    with pytest.raises(ValueError):
+        RustBoardState.from_dict(b.to_dict()).moves_played()
+
+
+@rust_only
+def test_sub_board_winners():
+    rng = random.Random(3)
+    for _ in range(200):
+        board = [[rng.choice((0, 0, 1, 2)) for _ in range(9)] for _ in range(9)]
+        expected = [RustBoardState._get_winner_of(row) for row in board]
+        assert RustBoardState._sub_board_winners(board) == expected
+
+    b = RustBoardState()
+    while b.get_legal_moves():
+        b = b.play(rng.choice(sorted(b.get_legal_moves())))
+        assert RustBoardState._sub_board_winners(b.board.tolist()) == b._win_state
+
+
+@rust_only
+def test_is_legal():
+    rng = random.Random(5)
+    cells = [(i, j) for i in range(3) for j in range(3)]
+    b = RustBoardState()
+    while b.get_legal_moves():
+        moves = b.get_legal_moves()
+        for c_move in itertools.product(cells, cells):
+            assert b.is_legal(c_move) == (c_move in moves)
+        b = b.play(rng.choice(sorted(moves)))
+
+    assert not b.is_legal(((3, 0), (0, 0)))
+
+
+@rust_only
+def test_fen_round_trip():
+    rng = random.Random(11)
+    b = RustBoardState()
+    assert b.fen() == "9/9/9/9/9/9/9/9/9 - 1 0"
+
+    while b.get_legal_moves():
+        assert RustBoardState.from_fen(b.fen()) == b
+        b = b.play(rng.choice(sorted(b.get_legal_moves())))
+    assert RustBoardState.from_fen(b.fen()) == b
+    assert RustBoardState().play(((1, 1), (0, 2))).fen() == "9/9/9/9/2X6/9/9/9/9 2 2 1"
+
+
+@rust_only
+@pytest.mark.parametrize("fen", [
+    "", "9/9/9/9/9/9/9/9 - 1 0", "8/9/9/9/9/9/9/9/9 - 1 0", "XXXXXXXXXX/9/9/9/9/9/9/9/9 - 1 0",
+    "9/9/9/9/9/9/9/9/9 9 1 0", "9/9/9/9/9/9/9/9/9 - 0 0", "9/9/9/9/9/9/9/9/9 - 1 -1", "A8/9/9/9/9/9/9/9/9 - 1 0",
+])
+def test_from_fen_invalid(fen):
+    with pytest.raises(ValueError):
+        RustBoardState.from_fen(fen)
+
+
+@rust_only
+def test_sub_board_mobility():
+    rng = random.Random(13)
+    b = RustBoardState()
+    assert b.sub_board_mobility() == [9] * 9
+
+    while b.get_legal_moves():
+        mobility = b.sub_board_mobility()
+        for idx, win in enumerate(b._win_state):
+            assert mobility[idx] == (0 if win != 0 else b.sub_board_counts(idx)[2])
+        if b._active_cell == -1:
+            assert sum(mobility) == len(b.legal_moves())
+        b = b.play(rng.choice(sorted(b.get_legal_moves())))
+
+
+@rust_only
+def test_board_is_read_only():
+    b = RustBoardState()
+    with pytest.raises(ValueError):
+        b.board[0, 0] = 1
+    assert b == RustBoardState()
+
+    with pytest.raises(ValueError):
+        b._board = np.zeros((10, 10), dtype=np.int64)
+    assert b == RustBoardState()
+
+
+@rust_only
+@pytest.mark.parametrize("key, value", [
+    ("active_cell", 9), ("active_cell", -2), ("active_cell", 100), ("curr_pid", 0), ("win_state", [3] + [0] * 8),
+])
+def test_from_dict_out_of_range(key, value):
+    data = RustBoardState().to_dict()
+    data[key] = value
+    with pytest.raises(ValueError):
+        RustBoardState.from_dict(data)
+
+    data = RustBoardState().to_dict()
+    data["board"][0][0] = 7
+    with pytest.raises(ValueError):
+        RustBoardState.from_dict(data)
+
+
+@rust_only
+@pytest.mark.parametrize("active_cell", [9, 81, -5])
+def test_active_cell_setter_out_of_range(active_cell):
+    b = RustBoardState()
+    with pytest.raises(ValueError):
+        b._active_cell = active_cell
+    assert b._active_cell == -1
+    assert len(b.get_legal_moves()) == 81
+
+
+@rust_only
+def test_from_msgpack_future_version():
+    # a payload of a later version, with one more field after the current ones
+    data = bytearray(RustBoardState().to_msgpack())
+    data[0], data[1] = 0x97, 0x02
+    data += b"\xc0"
+
+    with pytest.raises(GameEngines.StateLoadError, match="version 2"):
+        RustBoardState.from_msgpack(bytes(data))
+
+
+@rust_only
+@pytest.mark.parametrize("offset, value", [(5, 0x07), (-3, 0x09), (-3, 0x7f), (-1, 0x00)])
+def test_from_msgpack_invalid(offset, value):
+    # the payload is [version, board, win_state, active_cell, turn, curr_pid] with the 81 cells of
+    # the board as fixints after the array header
+    data = bytearray(RustBoardState().to_msgpack())
+    assert data[:5] == b"\x96\x01\xdc\x00\x51"
+    assert data[-3:] == b"\xff\x00\x01"
+
+    data[offset] = value
+    with pytest.raises(ValueError):
+        RustBoardState.from_msgpack(bytes(data))